@@ -1,13 +1,18 @@
 use bevy::prelude::{IntoSystem, Stage, State, SystemStage, World};
-use bevy_chess::model::{AllValidMoves, Piece, PieceColour, PieceKind, Square};
-use bevy_chess::systems::chess::{calculate_all_moves, GameState, PlayerTurn};
+use bevy_chess::model::{
+    AllValidMoves, CheckState, Piece, PieceColour, PieceKind, SpecialMoveData, Square,
+};
+use bevy_chess::systems::chess::headless::HeadlessGame;
+use bevy_chess::systems::chess::{
+    calculate_all_moves, GameState, MatingMove, PerformanceStats, PlayerTurn,
+};
 use criterion::*;
 
 fn calculate_moves_for_default_board(c: &mut Criterion) {
     c.bench_function("calculate moves for default board", |b| {
         let (mut world, mut system) = setup();
 
-        let ids = pieces()
+        let ids = Piece::starting_position()
             .into_iter()
             .map(|piece| world.spawn().insert(piece).id())
             .collect::<Vec<_>>();
@@ -23,12 +28,92 @@ fn calculate_moves_for_default_board(c: &mut Criterion) {
     });
 }
 
+fn calculate_moves_for_middlegame_board(c: &mut Criterion) {
+    c.bench_function("calculate moves for middlegame board", |b| {
+        let (mut world, mut system) = setup();
+
+        let mut special_move_data = world.get_resource_mut::<SpecialMoveData>().unwrap();
+        special_move_data.white_castling_data.king_moved = true;
+        special_move_data.black_castling_data.king_moved = true;
+
+        // Giuoco Pianissimo, after both sides have castled
+        let ids = pieces_from_ranks([
+            "r.bq.rk.", "ppp..ppp", "..np.n..", "..b.p...", "..B.P...", "..PP.N..", "PP...PPP",
+            "RNBQ.RK.",
+        ])
+        .into_iter()
+        .map(|piece| world.spawn().insert(piece).id())
+        .collect::<Vec<_>>();
+
+        b.iter(|| {
+            system.run(&mut world);
+        });
+
+        let all_moves = world.get_resource::<AllValidMoves>().unwrap();
+        ids.into_iter().for_each(|id| {
+            all_moves.get(id);
+        })
+    });
+}
+
+fn play_random_plies_from_the_default_board(c: &mut Criterion) {
+    c.bench_function("play 40 random plies from the default board", |b| {
+        let mut seed = 0;
+
+        b.iter_batched(
+            HeadlessGame::new,
+            |mut game| {
+                seed += 1;
+                // a game can end early, but 40 plies is rarely enough for that to happen
+                game.play_random_moves(seed, 40)
+            },
+            BatchSize::SmallInput,
+        );
+    });
+}
+
+/// ranks are listed from Black's back rank down to White's, with `.` for an empty square
+fn pieces_from_ranks(ranks: [&str; 8]) -> Vec<Piece> {
+    ranks
+        .into_iter()
+        .zip((0..8).rev())
+        .flat_map(|(row, rank)| {
+            row.chars().zip(0..).filter_map(move |(symbol, file)| {
+                let kind = match symbol.to_ascii_lowercase() {
+                    'k' => PieceKind::King,
+                    'q' => PieceKind::Queen,
+                    'r' => PieceKind::Rook,
+                    'b' => PieceKind::Bishop,
+                    'n' => PieceKind::Knight,
+                    'p' => PieceKind::Pawn,
+                    _ => return None,
+                };
+                let colour = if symbol.is_ascii_uppercase() {
+                    PieceColour::White
+                } else {
+                    PieceColour::Black
+                };
+
+                Some(Piece {
+                    colour,
+                    kind,
+                    square: Square::new(rank, file),
+                })
+            })
+        })
+        .collect()
+}
+
 fn setup() -> (World, SystemStage) {
     let mut world = World::new();
 
     world.insert_resource(AllValidMoves::default());
+    world.insert_resource(CheckState::default());
+    world.insert_resource::<Option<MatingMove>>(None);
+    world.insert_resource(PerformanceStats::default());
     world.insert_resource(PlayerTurn(PieceColour::Black));
     world.insert_resource(State::new(GameState::NothingSelected));
+    world.insert_resource(SpecialMoveData::default());
 
     let mut update_stage = SystemStage::parallel();
     update_stage.add_system_set(State::<GameState>::get_driver());
@@ -37,46 +122,11 @@ fn setup() -> (World, SystemStage) {
     (world, update_stage)
 }
 
-fn pieces() -> Vec<Piece> {
-    let back_row = [
-        PieceKind::Rook,
-        PieceKind::Knight,
-        PieceKind::Bishop,
-        PieceKind::Queen,
-        PieceKind::King,
-        PieceKind::Bishop,
-        PieceKind::Knight,
-        PieceKind::Rook,
-    ];
-    let front_row = |colour: PieceColour| {
-        (0..8)
-            .map(|file| Piece {
-                square: Square::new(colour.starting_front_rank(), file),
-                kind: PieceKind::Pawn,
-                colour,
-            })
-            .collect::<Vec<_>>()
-    };
-
-    back_row
-        .iter()
-        .enumerate()
-        .map(|(idx, kind)| Piece::white (
-            *kind,
-            Square::new(0, idx as _),
-        ))
-        .chain(front_row(PieceColour::White).into_iter())
-        .chain(front_row(PieceColour::Black).into_iter())
-        .chain(back_row.iter().enumerate().map(|(idx, kind)| Piece::black (
-            *kind,
-            Square::new(7, idx as _)
-        )))
-        .collect::<Vec<_>>()
-}
-
 criterion_group! {
     benches,
     calculate_moves_for_default_board,
+    calculate_moves_for_middlegame_board,
+    play_random_plies_from_the_default_board,
 }
 
 criterion_main!(benches);
@@ -1,15 +1,37 @@
 extern crate bevy_chess;
 
+use bevy::app::AppExit;
 use bevy::input::system::exit_on_esc_system;
 use bevy::prelude::*;
+use bevy_chess::model::{BoardScale, PieceColour};
 use bevy_chess::ui::UiPlugin;
 use bevy_mod_picking::{PickingCameraBundle, PickingPlugin};
-use bevy_chess::systems::chess::ChessPlugin;
-use bevy_chess::systems::orbit_camera::{GameCamera, OrbitCameraPlugin};
+use bevy_chess::systems::chess::scripted_moves::ScriptedMoves;
+use bevy_chess::systems::chess::{ChessPlugin, GameState};
+use bevy_chess::systems::lighting::LightingPlugin;
+use bevy_chess::systems::settings::SettingsPlugin;
+use bevy_chess::systems::orbit_camera::{
+    CameraSettings, GameCamera, OrbitCameraPlugin, ViewingSide,
+};
 
 fn main() {
+    let viewing_side = if std::env::args().any(|arg| arg == "--black") {
+        PieceColour::Black
+    } else {
+        PieceColour::White
+    };
+    let camera_settings = if std::env::args().any(|arg| arg == "--top-down") {
+        CameraSettings::top_down()
+    } else {
+        CameraSettings::default()
+    };
+    let scripted_moves = scripted_moves();
+
     App::new()
         .insert_resource(Msaa { samples: 4 })
+        .insert_resource(ViewingSide(viewing_side))
+        .insert_resource(camera_settings)
+        .insert_resource(scripted_moves)
         .insert_resource(WindowDescriptor {
             width: 1600.0,
             height: 800.0,
@@ -20,25 +42,61 @@ fn main() {
         .add_plugin(PickingPlugin)
         .add_plugin(ChessPlugin)
         .add_plugin(OrbitCameraPlugin)
+        .add_plugin(LightingPlugin)
         .add_plugin(UiPlugin)
+        .add_plugin(SettingsPlugin)
         .add_startup_system(setup.system())
         .add_system(exit_on_esc_system.system())
+        .add_system(abort_scripted_moves.system())
         .run();
 }
 
-fn setup(mut commands: Commands) {
+fn setup(
+    mut commands: Commands,
+    board_scale: Res<BoardScale>,
+    viewing_side: Res<ViewingSide>,
+    window: Res<WindowDescriptor>,
+    camera_settings: Res<CameraSettings>,
+) {
+    let mut camera = PerspectiveCameraBundle::default();
+    camera.perspective_projection.fov = camera_settings.fov;
+    camera.transform = camera_settings.transform(window.width / window.height, *board_scale);
+    let eye = camera.transform.translation;
+
     commands
-        .spawn_bundle(PerspectiveCameraBundle::default())
+        .spawn_bundle(camera)
         .insert_bundle(PickingCameraBundle::default())
-        .insert(GameCamera::new(Vec3::new(0.0, 13.0, -9.0), Vec3::ZERO));
+        .insert(GameCamera::new(eye, camera_settings.target).viewed_from(viewing_side.0));
+}
 
-    commands.spawn_bundle(PointLightBundle {
-        transform: Transform::from_xyz(1.0, 10.0, 2.0),
-        point_light: PointLight {
-            intensity: 2000.0,
-            range: 60.0,
-            ..Default::default()
-        },
-        ..Default::default()
+/// `--moves <file>` plays the moves in the file at startup, e.g. to reproduce a bug report
+fn scripted_moves() -> ScriptedMoves {
+    let args = std::env::args().collect::<Vec<_>>();
+    let Some(index) = args.iter().position(|arg| arg == "--moves") else { return ScriptedMoves::default() };
+    let path = args.get(index + 1).unwrap_or_else(|| {
+        eprintln!("--moves needs a file of moves to play");
+        std::process::exit(1);
     });
+
+    ScriptedMoves::read(path.as_ref()).unwrap_or_else(|error| {
+        eprintln!("Couldn't read moves from {}: {}", path, error);
+        std::process::exit(1);
+    })
+}
+
+/// quits at the first scripted move that can't be played, rather than carrying on from a position
+/// the script never meant to reach
+fn abort_scripted_moves(
+    mut scripted_moves: ResMut<ScriptedMoves>,
+    game_state: Res<State<GameState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if game_state.current().is_game_over() && !scripted_moves.is_finished() {
+        scripted_moves.abort("the game is already over");
+    }
+
+    if let Some(error) = scripted_moves.error() {
+        error!("{}", error);
+        exit.send(AppExit);
+    }
 }
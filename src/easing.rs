@@ -8,6 +8,28 @@ pub fn sigmoid(k: f32) -> Box<dyn Fn(f32) -> f32> {
     Box::new(move |x: f32| (x - (k * x)) / (k - (2.0 * k * x.abs()) + 1.0))
 }
 
+/// unlike `sigmoid`, this takes and returns values in 0..1: it rushes towards 1, then bounces off it
+/// a few times, each bounce smaller than the last, before settling there
+///
+/// see: https://easings.net/#easeOutBounce
+pub fn bounce(x: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+
+    if x < 1.0 / D {
+        N * x * x
+    } else if x < 2.0 / D {
+        let x = x - 1.5 / D;
+        N * x * x + 0.75
+    } else if x < 2.5 / D {
+        let x = x - 2.25 / D;
+        N * x * x + 0.9375
+    } else {
+        let x = x - 2.625 / D;
+        N * x * x + 0.984375
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -145,4 +167,15 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn bounce_should_start_at_0_and_settle_at_1_without_overshooting() {
+        assert_eq!(bounce(0.0), 0.0);
+        assert!((bounce(1.0) - 1.0).abs() < 1e-6);
+
+        let samples = (0..=100).map(|x| bounce(x as f32 / 100.0)).collect::<Vec<_>>();
+        assert!(samples.iter().all(|y| (0.0..=1.0 + 1e-6).contains(y)));
+        // every bounce falls back a little way before rising again
+        assert!(samples.windows(2).any(|pair| pair[1] < pair[0]));
+    }
 }
@@ -8,9 +8,14 @@ mod moves_calculator;
 
 pub mod easing;
 pub mod model;
+pub mod notation;
+pub mod random;
+pub mod random_position;
 pub mod ui;
 
 pub mod systems {
     pub mod orbit_camera;
     pub mod chess;
+    pub mod lighting;
+    pub mod settings;
 }
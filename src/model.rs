@@ -3,16 +3,41 @@ use std::fmt::Formatter;
 use bevy::math::Vec3;
 use bevy::prelude::{Component, Entity};
 use bevy::utils::HashMap;
+use crate::moves_calculator::{calculate_valid_moves, CalculatorResult};
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
     mod board_tests;
+    mod move_tests;
+    mod path_tests;
+    mod piece_kind_tests;
     mod piece_tests;
+    mod square_tests;
+    mod valid_moves_tests;
 }
 
-#[derive(Debug, Copy, Clone, Component)]
+/// the number of ranks and files on the board
+pub const BOARD_SIZE: u8 = 8;
+
+/// the file both Kings start on, and have to still be on to castle
+pub const KING_STARTING_FILE: u8 = 4;
+
+/// the order of the pieces along each player's back rank at the start of a game
+pub const STARTING_BACK_RANK: [PieceKind; BOARD_SIZE as usize] = [
+    PieceKind::Rook,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Queen,
+    PieceKind::King,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+    PieceKind::Rook,
+];
+
+/// sorts by colour, then kind, then square, so a set of pieces always comes out in the same order
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Component)]
 pub struct Piece {
     pub colour: PieceColour,
     pub kind: PieceKind,
@@ -35,9 +60,31 @@ impl Piece {
             square,
         }
     }
+
+    /// every piece on the board at the start of a game; White's pieces come first
+    pub fn starting_position() -> Vec<Piece> {
+        PieceColour::all()
+            .into_iter()
+            .flat_map(|colour| {
+                STARTING_BACK_RANK
+                    .into_iter()
+                    .zip(0..)
+                    .map(move |(kind, file)| Piece {
+                        colour,
+                        kind,
+                        square: Square::new(colour.starting_back_rank(), file),
+                    })
+                    .chain((0..BOARD_SIZE).map(move |file| Piece {
+                        colour,
+                        kind: PieceKind::Pawn,
+                        square: Square::new(colour.starting_front_rank(), file),
+                    }))
+            })
+            .collect()
+    }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum PieceKind {
     King,
     Queen,
@@ -46,13 +93,74 @@ pub enum PieceKind {
     Rook,
     Pawn,
 }
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+
+impl PieceKind {
+    /// every kind of piece, in declaration order
+    pub fn all() -> [PieceKind; 6] {
+        [
+            PieceKind::King,
+            PieceKind::Queen,
+            PieceKind::Bishop,
+            PieceKind::Knight,
+            PieceKind::Rook,
+            PieceKind::Pawn,
+        ]
+    }
+
+    /// material value in pawns; the King can never be traded, so it's worth nothing
+    pub fn value(&self) -> i32 {
+        match self {
+            PieceKind::King => 0,
+            PieceKind::Queen => 9,
+            PieceKind::Rook => 5,
+            PieceKind::Bishop => 3,
+            PieceKind::Knight => 3,
+            PieceKind::Pawn => 1,
+        }
+    }
+
+    /// FEN uses upper case for White pieces and lower case for Black pieces
+    pub fn fen_char(&self, colour: PieceColour) -> char {
+        let letter = match self {
+            PieceKind::King => 'K',
+            PieceKind::Queen => 'Q',
+            PieceKind::Bishop => 'B',
+            PieceKind::Knight => 'N',
+            PieceKind::Rook => 'R',
+            PieceKind::Pawn => 'P',
+        };
+
+        match colour {
+            PieceColour::White => letter,
+            PieceColour::Black => letter.to_ascii_lowercase(),
+        }
+    }
+
+    /// the prefix used in standard algebraic notation; pawn moves are written without one
+    pub fn algebraic_letter(&self) -> &'static str {
+        match self {
+            PieceKind::King => "K",
+            PieceKind::Queen => "Q",
+            PieceKind::Bishop => "B",
+            PieceKind::Knight => "N",
+            PieceKind::Rook => "R",
+            PieceKind::Pawn => "",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum PieceColour {
     White,
     Black,
 }
 
 impl PieceColour {
+    /// both colours, White first
+    pub fn all() -> [PieceColour; 2] {
+        [PieceColour::White, PieceColour::Black]
+    }
+
     #[must_use]
     pub fn opposite(&self) -> Self {
         match self {
@@ -72,20 +180,20 @@ impl PieceColour {
     pub fn starting_front_rank(&self) -> u8 {
         match self {
             PieceColour::White => 1,
-            PieceColour::Black => 6,
+            PieceColour::Black => BOARD_SIZE - 2,
         }
     }
 
     pub fn starting_back_rank(&self) -> u8 {
         match self {
             PieceColour::White => 0,
-            PieceColour::Black => 7,
+            PieceColour::Black => BOARD_SIZE - 1,
         }
     }
 
     pub fn final_rank(&self) -> u8 {
         match self {
-            PieceColour::White => 7,
+            PieceColour::White => BOARD_SIZE - 1,
             PieceColour::Black => 0,
         }
     }
@@ -178,6 +286,38 @@ impl PiecePath {
             .collect()
     }
 
+    /// the first piece along this path, if any, regardless of colour
+    pub fn blocking_piece(&self) -> Option<Obstruction> {
+        self.potential_moves.iter().find_map(|potential_move| {
+            potential_move.blocked_by.map(|blockage| Obstruction {
+                square: potential_move.target_square,
+                colour: blockage,
+            })
+        })
+    }
+
+    /// every square along this path up to and including the first piece in the way,
+    /// whichever colour it is
+    pub fn attacked_squares(&self) -> impl Iterator<Item = Square> + '_ {
+        self.potential_moves
+            .iter()
+            .scan(false, |blocked, potential_move| {
+                if *blocked {
+                    return None;
+                };
+
+                *blocked = potential_move.blocked_by.is_some();
+                Some(potential_move.target_square)
+            })
+    }
+
+    /// every square along this path, ignoring any pieces in the way
+    pub fn pseudo_legal_squares(&self) -> impl Iterator<Item = Square> + '_ {
+        self.potential_moves
+            .iter()
+            .map(|potential_move| potential_move.target_square)
+    }
+
     pub fn contains(&self, square: Square) -> bool {
         self.potential_moves
             .iter()
@@ -219,6 +359,11 @@ pub struct PawnMoves {
 
 impl Piece {
     pub fn valid_moves(&self, board: &BoardState) -> Vec<PiecePath> {
+        self.valid_moves_on_board(board, BOARD_SIZE)
+    }
+
+    /// only the sliding and jumping pieces respect `board_size` - pawns always play on a standard board
+    fn valid_moves_on_board(&self, board: &BoardState, board_size: u8) -> Vec<PiecePath> {
         let potential_move = |(x, y): (u8, u8)| PotentialMove {
             kind: MoveKind::Standard,
             target_square: (x, y).into(),
@@ -227,7 +372,7 @@ impl Piece {
 
         let up = || {
             PiecePath::from_iterator(
-                ((self.square.rank + 1)..8)
+                ((self.square.rank + 1)..board_size)
                     .map(|new_rank| potential_move((new_rank, self.square.file))),
                 self.colour,
             )
@@ -253,7 +398,7 @@ impl Piece {
 
         let right = || {
             PiecePath::from_iterator(
-                ((self.square.file + 1)..8)
+                ((self.square.file + 1)..board_size)
                     .map(|new_rank| potential_move((self.square.rank, new_rank))),
                 self.colour,
             )
@@ -261,7 +406,7 @@ impl Piece {
 
         let up_left = || {
             PiecePath::from_iterator(
-                ((self.square.rank + 1)..8)
+                ((self.square.rank + 1)..board_size)
                     .filter_map(|new_rank| {
                         let diff = self.square.rank.abs_diff(new_rank);
                         (diff <= self.square.file).then(|| (new_rank, self.square.file - diff))
@@ -273,10 +418,10 @@ impl Piece {
 
         let up_right = || {
             PiecePath::from_iterator(
-                ((self.square.rank + 1)..8)
+                ((self.square.rank + 1)..board_size)
                     .filter_map(|new_rank| {
                         let new_file = self.square.file + self.square.rank.abs_diff(new_rank);
-                        (new_file < 8).then(|| (new_rank, new_file))
+                        (new_file < board_size).then(|| (new_rank, new_file))
                     })
                     .map(potential_move),
                 self.colour,
@@ -302,7 +447,7 @@ impl Piece {
                     .rev()
                     .filter_map(|new_rank| {
                         let new_file = self.square.file + self.square.rank.abs_diff(new_rank);
-                        (new_file < 8).then(|| (new_rank, new_file))
+                        (new_file < board_size).then(|| (new_rank, new_file))
                     })
                     .map(potential_move),
                 self.colour,
@@ -312,7 +457,9 @@ impl Piece {
         let (rank, file) = (self.square.rank as i8, self.square.file as i8);
 
         let is_on_board = |(rank, file): (i8, i8)| {
-            ((0..8).contains(&rank) && (0..8).contains(&file)).then(|| (rank as u8, file as u8))
+            let board_size = board_size as i8;
+            ((0..board_size).contains(&rank) && (0..board_size).contains(&file))
+                .then(|| (rank as u8, file as u8))
         };
 
         match self.kind {
@@ -384,6 +531,27 @@ impl Piece {
         }
     }
 
+    /// every square this piece is attacking, including squares occupied by pieces of the same colour
+    ///
+    /// unlike `valid_moves`, a pawn attacks both of its diagonals whether or not there's anything on
+    /// them, but never the square in front of it
+    pub fn attacks(&self, board: &BoardState) -> Vec<Square> {
+        if self.kind == PieceKind::Pawn {
+            let pawn_moves = self.pawn_moves(board, true);
+
+            [pawn_moves.attack_left, pawn_moves.attack_right]
+                .into_iter()
+                .flatten()
+                .map(|potential_move| potential_move.target_square)
+                .collect()
+        } else {
+            self.valid_moves(board)
+                .iter()
+                .flat_map(|path| path.attacked_squares().collect::<Vec<_>>())
+                .collect()
+        }
+    }
+
     /// set `attack_empty_squares` to `false` when calculating potential moves, and `true` when checking if a move is safe
     pub fn pawn_moves(&self, board: &BoardState, attack_empty_squares: bool) -> PawnMoves {
         if self.kind != PieceKind::Pawn {
@@ -418,10 +586,8 @@ impl Piece {
             let advance_two = (self.square.rank == self.colour.starting_front_rank()
                 && board.get((move_one, file).into()).is_none()
                 && board.get((move_two, file).into()).is_none())
-            .then_some(PotentialMove::new(
-                Move::pawn_double_step((move_two, file).into()),
-                None,
-            ));
+            // note: `move_two` is off the board for a pawn on the second-last rank, so this has to be lazy
+            .then(|| PotentialMove::new(Move::pawn_double_step((move_two, file).into()), None));
 
             let left_diagonal_occupied = || {
                 board
@@ -436,7 +602,7 @@ impl Piece {
                     .get((move_one, file + 1).into())
                     .contains(&self.colour.opposite())
             };
-            let attack_right = (file != 7 && (attack_empty_squares || right_diagonal_occupied()))
+            let attack_right = (file != BOARD_SIZE - 1 && (attack_empty_squares || right_diagonal_occupied()))
                 .then(|| PotentialMove::new(Move::standard((move_one, file + 1).into()), None));
 
             PawnMoves {
@@ -450,14 +616,23 @@ impl Piece {
 }
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BoardState {
-    squares: [Option<PieceColour>; 64],
+    squares: [Option<PieceColour>; (BOARD_SIZE * BOARD_SIZE) as usize],
 }
 
 impl BoardState {
+    /// the occupancy of the board at the start of a game
+    pub fn standard() -> Self {
+        Piece::starting_position().iter().collect()
+    }
+
     pub fn get(&self, square: Square) -> &Option<PieceColour> {
-        &self.squares[(square.rank * 8 + square.file) as usize]
+        &self.squares[(square.rank * BOARD_SIZE + square.file) as usize]
+    }
+
+    fn set(&mut self, square: Square, occupant: Option<PieceColour>) {
+        self.squares[(square.rank * BOARD_SIZE + square.file) as usize] = occupant;
     }
 
     #[cfg(test)]
@@ -480,16 +655,231 @@ impl<const N: usize> From<[Piece; N]> for BoardState {
 
 impl<'piece> FromIterator<&'piece Piece> for BoardState {
     fn from_iter<T: IntoIterator<Item = &'piece Piece>>(pieces: T) -> Self {
-        let mut squares = [None; 64];
+        let mut squares = [None; (BOARD_SIZE * BOARD_SIZE) as usize];
         pieces.into_iter().for_each(|piece| {
-            squares[(piece.square.rank * 8 + piece.square.file) as usize] = Some(piece.colour);
+            squares[(piece.square.rank * BOARD_SIZE + piece.square.file) as usize] = Some(piece.colour);
         });
 
         Self { squares }
     }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone, Component)]
+/// the occupancy after the piece on `from` makes the move, without needing to play it out in the ECS
+///
+/// a promotion doesn't change which squares are occupied, so it's the same as any other pawn move
+pub fn apply_to_board(board: &BoardState, from: Square, move_: Move) -> BoardState {
+    let mut board = board.clone();
+    let colour = *board.get(from);
+    let target = move_.target_square;
+
+    board.set(from, None);
+    match move_.kind {
+        MoveKind::Standard | MoveKind::PawnDoubleStep => board.set(target, colour),
+        MoveKind::EnPassant { .. } => {
+            // the captured pawn is beside the moving pawn, not on the square it moves to
+            board.set(Square::new(from.rank, target.file), None);
+            board.set(target, colour);
+        }
+        MoveKind::Castle {
+            rook_position,
+            king_target_y,
+            rook_target_y,
+            ..
+        } => {
+            board.set(rook_position, None);
+            board.set(Square::new(from.rank, rook_target_y), colour);
+            board.set(Square::new(from.rank, king_target_y), colour);
+        }
+    }
+
+    board
+}
+
+/// every square with more than one piece on it, which should never happen
+pub fn overlapping_squares(pieces: &[Piece]) -> Vec<Square> {
+    pieces
+        .iter()
+        .enumerate()
+        // only the second piece on a square is counted, so each square is only listed once
+        .filter(|(index, piece)| {
+            pieces[..*index]
+                .iter()
+                .filter(|other| other.square == piece.square)
+                .count()
+                == 1
+        })
+        .map(|(_, piece)| piece.square)
+        .collect()
+}
+
+/// what a square in a position editor holds after it's clicked: an empty square gets one of White's
+/// pieces, each click moves on to the next kind and then to Black's pieces, and the last of those
+/// empties the square again
+pub fn next_square_contents(
+    contents: Option<(PieceColour, PieceKind)>,
+) -> Option<(PieceColour, PieceKind)> {
+    let cycle = PieceColour::all()
+        .into_iter()
+        .flat_map(|colour| PieceKind::all().map(|kind| (colour, kind)))
+        .collect::<Vec<_>>();
+
+    match contents {
+        None => cycle.first().copied(),
+        Some(contents) => cycle
+            .iter()
+            .skip_while(|other| **other != contents)
+            .nth(1)
+            .copied(),
+    }
+}
+
+/// the total value of the colour's pieces, in pawns
+pub fn material(pieces: &[Piece], colour: PieceColour) -> i32 {
+    pieces
+        .iter()
+        .filter(|piece| piece.colour == colour)
+        .map(|piece| piece.kind.value())
+        .sum()
+}
+
+/// whether any sequence of legal moves, however bad the opponent's, ends with this colour
+/// checkmating - e.g. whether running out of time or resigning against it should lose or draw
+///
+/// unlike the position being a draw, this is one-sided: a lone King can't win, even if the other
+/// side still has everything
+pub fn can_possibly_win(colour: PieceColour, pieces: &[Piece]) -> bool {
+    let (own, theirs): (Vec<&Piece>, Vec<&Piece>) = pieces
+        .iter()
+        .filter(|piece| piece.kind != PieceKind::King)
+        .partition(|piece| piece.colour == colour);
+
+    if own.iter().any(|piece| {
+        matches!(
+            piece.kind,
+            PieceKind::Pawn | PieceKind::Rook | PieceKind::Queen
+        )
+    }) {
+        return true;
+    }
+
+    // Bishops that all stand on the same colour square can never cover the King's escape squares
+    let first_square_colour = own.first().map(|piece| piece.square.is_dark());
+    let only_same_coloured_bishops = own.iter().chain(theirs.iter()).all(|piece| {
+        piece.kind == PieceKind::Bishop && Some(piece.square.is_dark()) == first_square_colour
+    });
+
+    match own.len() {
+        0 => false,
+        _ if only_same_coloured_bishops => false,
+        // a single minor piece can only mate if the opponent has something to block their own King in with
+        1 => !theirs.is_empty(),
+        _ => true,
+    }
+}
+
+/// where the game stands for the player about to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionStatus {
+    Ongoing,
+    Check,
+    Checkmate,
+    Stalemate,
+}
+
+/// the same check, checkmate, and stalemate detection as `calculate_all_moves`, without needing the
+/// ECS, e.g. for the end of a line in a search
+///
+/// the entities have to be the ones `special` refers to, or en passant won't be recognised
+///
+/// the "only Kings left" draw isn't detected, since there's still a move to make
+pub fn position_status(
+    board: &BoardState,
+    pieces: &[(Entity, Piece)],
+    turn: PieceColour,
+    special: &SpecialMoveData,
+) -> PositionStatus {
+    let (player_pieces, opposite_pieces): (Vec<_>, Vec<_>) = pieces
+        .iter()
+        .map(|(entity, piece)| (*entity, piece))
+        .partition(|(_, piece)| piece.colour == turn);
+
+    match calculate_valid_moves(
+        turn,
+        special,
+        &player_pieces,
+        &opposite_pieces,
+        board.clone(),
+    ) {
+        CalculatorResult::Stalemate => PositionStatus::Stalemate,
+        CalculatorResult::Checkmate(_) => PositionStatus::Checkmate,
+        CalculatorResult::Ok(_, check) if check.is_check() => PositionStatus::Check,
+        CalculatorResult::Ok(..) => PositionStatus::Ongoing,
+    }
+}
+
+/// how many legal moves the colour has, which is only known for the player whose turn it is
+///
+/// the other player's moves aren't calculated, so this is 0 for them - see `pseudo_legal_mobility`
+pub fn mobility(moves: &AllValidMoves, colour: PieceColour) -> u32 {
+    if moves.colour() == Some(colour) {
+        moves.total_move_count() as u32
+    } else {
+        0
+    }
+}
+
+/// how many moves the colour's pieces could make, ignoring check, pins, and the special moves
+///
+/// unlike `mobility`, this works for either player, e.g. for the one who has just moved
+pub fn pseudo_legal_mobility(pieces: &[Piece], colour: PieceColour) -> u32 {
+    let board = pieces.iter().collect::<BoardState>();
+
+    pieces
+        .iter()
+        .filter(|piece| piece.colour == colour)
+        .flat_map(|piece| piece.valid_moves(&board))
+        .map(|path| path.legal_path().count() as u32)
+        .sum()
+}
+
+/// every piece of the colour attacking the square, whether or not it could legally capture there
+///
+/// the square may be empty, or occupied by either colour - a piece defending one of its own
+/// counts as attacking it
+pub fn attackers_of(
+    square: Square,
+    colour: PieceColour,
+    pieces: &[(Entity, Piece)],
+) -> Vec<Entity> {
+    let board = pieces
+        .iter()
+        .map(|(_, piece)| piece)
+        .collect::<BoardState>();
+
+    pieces
+        .iter()
+        .filter(|(_, piece)| piece.colour == colour && piece.square != square)
+        .filter(|(_, piece)| piece.attacks(&board).contains(&square))
+        .map(|(entity, _)| *entity)
+        .collect()
+}
+
+/// where a ray, e.g. from the camera through the cursor, meets the plane the board lies in (y = 0,
+/// whatever the board's scale), if it's pointing towards it at all
+pub fn board_plane_intersection(origin: Vec3, direction: Vec3) -> Option<Vec3> {
+    if direction.y.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let distance = -origin.y / direction.y;
+    (distance >= 0.0).then(|| origin + (direction * distance))
+}
+
+/// how far the middle of the a1 square is from the middle of the board, in squares
+const CENTRE_OFFSET: f32 = (BOARD_SIZE - 1) as f32 / 2.0;
+
+/// sorts rank by rank from a1, the same order as `Square::all`
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone, Component)]
 pub struct Square {
     pub rank: u8,
     pub file: u8,
@@ -497,7 +887,12 @@ pub struct Square {
 
 impl Square {
     pub fn new(rank: u8, file: u8) -> Self {
-        assert!(rank <= 7 && file <= 7, "({}, {}) is out of bounds", rank, file);
+        assert!(
+            rank < BOARD_SIZE && file < BOARD_SIZE,
+            "({}, {}) is out of bounds",
+            rank,
+            file
+        );
 
         Self {
             rank,
@@ -505,14 +900,101 @@ impl Square {
         }
     }
 
-    pub fn from_translation(translation: Vec3) -> Self {
-        let rank = (translation.z + 3.5).round() as u8;
-        let file = (translation.x + 3.5).round() as u8;
+    /// reads a square's name, e.g. "e4", or `None` if it isn't one
+    pub fn from_algebraic(name: &str) -> Option<Self> {
+        let &[file, rank] = name.as_bytes() else { return None };
+        let file = file.checked_sub(b'a').filter(|file| *file < BOARD_SIZE)?;
+        let rank = rank.checked_sub(b'1').filter(|rank| *rank < BOARD_SIZE)?;
+
+        Some(Self::new(rank, file))
+    }
+
+    /// the letter of the square's file, e.g. 'e' for e4
+    pub fn file_name(&self) -> char {
+        (b'a' + self.file) as char
+    }
+
+    /// every square on the board, rank by rank from a1 to h8
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..BOARD_SIZE).flat_map(|rank| (0..BOARD_SIZE).map(move |file| Square { rank, file }))
+    }
+
+    /// every square a King on this square could step to
+    pub fn neighbours(&self) -> impl Iterator<Item = Square> {
+        let (rank, file) = (self.rank as i8, self.file as i8);
+
+        (-1..=1)
+            .flat_map(move |rank_offset| {
+                (-1..=1).map(move |file_offset| (rank_offset, file_offset))
+            })
+            .filter(|offset| *offset != (0, 0))
+            .filter_map(move |(rank_offset, file_offset)| {
+                let (rank, file) = (rank + rank_offset, file + file_offset);
+                let board_size = BOARD_SIZE as i8;
+                ((0..board_size).contains(&rank) && (0..board_size).contains(&file))
+                    .then(|| Square::new(rank as u8, file as u8))
+            })
+    }
+
+    /// a1 (i.e. (0, 0)) is a dark square
+    pub fn is_dark(&self) -> bool {
+        (self.rank + self.file) % 2 == 0
+    }
+
+    /// the number of moves a King would need to get from one square to the other
+    pub fn chebyshev_distance(&self, other: Square) -> u8 {
+        self.rank
+            .abs_diff(other.rank)
+            .max(self.file.abs_diff(other.file))
+    }
+
+    pub fn from_translation(translation: Vec3, scale: BoardScale) -> Self {
+        let rank = (translation.z / scale.0 + CENTRE_OFFSET).round() as u8;
+        let file = (translation.x / scale.0 + CENTRE_OFFSET).round() as u8;
         Self { rank, file }
     }
 
-    pub fn to_translation(self) -> Vec3 {
-        (self.file as f32 - 3.5, 0.0, self.rank as f32 - 3.5).into()
+    /// the square whose edges contain a point on the board, or `None` if it's off the edge
+    ///
+    /// unlike `from_translation`, which is for pieces sitting in the middle of their squares, this is
+    /// exact right up to the boundary between two squares
+    pub fn at_board_point(point: Vec3, scale: BoardScale) -> Option<Self> {
+        let index = |coordinate: f32| {
+            let index = (coordinate / scale.0 + (BOARD_SIZE as f32 / 2.0)).floor();
+            (0.0..BOARD_SIZE as f32)
+                .contains(&index)
+                .then(|| index as u8)
+        };
+
+        Some(Self {
+            rank: index(point.z)?,
+            file: index(point.x)?,
+        })
+    }
+
+    pub fn to_translation(self, scale: BoardScale) -> Vec3 {
+        Vec3::new(
+            self.file as f32 - CENTRE_OFFSET,
+            0.0,
+            self.rank as f32 - CENTRE_OFFSET,
+        ) * scale.0
+    }
+}
+
+/// the square's name, e.g. "e4"
+impl core::fmt::Display for Square {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.file_name(), self.rank + 1)
+    }
+}
+
+/// the width of a single square in world units; the board and piece meshes are scaled to match
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoardScale(pub f32);
+
+impl Default for BoardScale {
+    fn default() -> Self {
+        BoardScale(1.0)
     }
 }
 
@@ -587,6 +1069,21 @@ impl Move {
         }
     }
 
+    /// where the King ends up, for a castling move; the move itself targets the Rook's square
+    pub fn castling_destination(&self) -> Option<Square> {
+        if let MoveKind::Castle { king_target_y, .. } = self.kind {
+            Some(Square::new(self.target_square.rank, king_target_y))
+        } else {
+            None
+        }
+    }
+
+    /// whether the move takes one of the opponent's pieces - en passant captures onto an empty square
+    pub fn is_capture(&self, board: &BoardState, colour: PieceColour) -> bool {
+        matches!(self.kind, MoveKind::EnPassant { .. })
+            || board.get(self.target_square).contains(&colour.opposite())
+    }
+
     pub fn queenside_castle(square: Square, rook_id: Entity, rook: Piece) -> Self {
         Move {
             target_square: square,
@@ -617,6 +1114,21 @@ pub enum MoveKind {
     },
 }
 
+impl core::fmt::Display for MoveKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                MoveKind::Standard => "Standard",
+                MoveKind::PawnDoubleStep => "Pawn double step",
+                MoveKind::EnPassant { .. } => "En passant",
+                MoveKind::Castle { kingside: true, .. } => "Kingside castle",
+                MoveKind::Castle { kingside: false, .. } => "Queenside castle",
+            }
+        )
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub struct LastPawnDoubleStep {
@@ -624,14 +1136,62 @@ pub struct LastPawnDoubleStep {
     pub square: Square,
 }
 
+impl LastPawnDoubleStep {
+    /// the square the pawn passed over, which is where it would be taken en passant
+    pub fn skipped_square(&self, colour: PieceColour) -> Square {
+        let rank = self.square.rank as i8 - colour.pawn_direction();
+        Square::new(rank as u8, self.square.file)
+    }
+}
+
+/// the square `turn` could take a pawn on en passant, i.e. the square it skipped over, but only if
+/// one of `turn`'s pawns is beside it to make the capture
+///
+/// a capture that would leave `turn`'s King in check still counts, since ruling that out means
+/// calculating every move
+pub fn en_passant_square(
+    pieces: &[Piece],
+    turn: PieceColour,
+    special_move_data: &SpecialMoveData,
+) -> Option<Square> {
+    let double_step = special_move_data.last_pawn_double_step.as_ref()?;
+    let can_capture = pieces.iter().any(|piece| {
+        piece.colour == turn
+            && piece.kind == PieceKind::Pawn
+            && piece.square.rank == double_step.square.rank
+            && piece.square.file.abs_diff(double_step.square.file) == 1
+    });
+
+    can_capture.then(|| double_step.skipped_square(turn.opposite()))
+}
+
+/// the most recent move, as it was played (i.e. `piece` is still on the square it moved from)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LastMove {
+    pub piece_id: Entity,
+    pub piece: Piece,
+    pub target_square: Square,
+}
+
 #[derive(Debug, Default)]
 pub struct SpecialMoveData {
+    pub last_move: Option<LastMove>,
     pub last_pawn_double_step: Option<LastPawnDoubleStep>,
     pub white_castling_data: CastlingData,
     pub black_castling_data: CastlingData,
 }
 
 impl SpecialMoveData {
+    /// the castling rights each player could still have in a position that was set up rather than
+    /// played, with no last move to capture en passant
+    pub fn from_position(pieces: &[Piece]) -> Self {
+        Self {
+            white_castling_data: CastlingData::from_position(PieceColour::White, pieces),
+            black_castling_data: CastlingData::from_position(PieceColour::Black, pieces),
+            ..Default::default()
+        }
+    }
+
     pub fn castling_data(&self, turn: PieceColour) -> &CastlingData {
         if turn == PieceColour::White {
             &self.white_castling_data
@@ -656,16 +1216,118 @@ pub struct CastlingData {
     pub queenside_rook_moved: bool,
 }
 
+impl CastlingData {
+    /// what can be told about a position that was set up rather than played: a King or Rook that
+    /// isn't on its starting square has moved, and a missing Rook has either moved or been taken
+    pub fn from_position(colour: PieceColour, pieces: &[Piece]) -> Self {
+        let back_rank = colour.starting_back_rank();
+        let is_on = |kind, file| {
+            pieces.contains(&Piece {
+                colour,
+                kind,
+                square: Square::new(back_rank, file),
+            })
+        };
+
+        Self {
+            king_moved: !is_on(PieceKind::King, KING_STARTING_FILE),
+            kingside_rook_moved: !is_on(PieceKind::Rook, BOARD_SIZE - 1),
+            queenside_rook_moved: !is_on(PieceKind::Rook, 0),
+        }
+    }
+}
+
+/// everything that makes two positions the same for threefold repetition: where each piece is,
+/// whose turn it is, which castling moves are still allowed, and whether a pawn can be taken en passant
+///
+/// a double step only makes a difference if it can actually be taken, so otherwise the position is
+/// the same as if the pawn had got there one square at a time
+///
+/// the whole position is compared, so two different positions can't be counted as a repetition just
+/// because their hashes collide
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PositionKey {
+    /// sorted, so the order the pieces were listed in doesn't matter
+    pieces: Vec<Piece>,
+    turn: PieceColour,
+    /// (kingside, queenside) for White, then for Black
+    castling_rights: [(bool, bool); 2],
+    en_passant: Option<Square>,
+}
+
+impl PositionKey {
+    pub fn new(pieces: &[Piece], turn: PieceColour, special_move_data: &SpecialMoveData) -> Self {
+        let en_passant = en_passant_square(pieces, turn, special_move_data);
+        let mut pieces = pieces.to_vec();
+        pieces.sort();
+
+        let castling_rights = PieceColour::all().map(|colour| {
+            let castling_data = special_move_data.castling_data(colour);
+            (
+                !castling_data.king_moved && !castling_data.kingside_rook_moved,
+                !castling_data.king_moved && !castling_data.queenside_rook_moved,
+            )
+        });
+
+        Self {
+            pieces,
+            turn,
+            castling_rights,
+            en_passant,
+        }
+    }
+}
+
+/// the pieces putting the current player's King in check, if any
+#[derive(Default, Debug, PartialEq)]
+pub struct CheckState {
+    pub king_square: Option<Square>,
+    pub attackers: Vec<Square>,
+}
+
+impl CheckState {
+    pub fn is_check(&self) -> bool {
+        !self.attackers.is_empty()
+    }
+
+    /// (attacker, King) pairs - there will be two of these when a move causes double check
+    pub fn attack_lines(&self) -> Vec<(Square, Square)> {
+        self.king_square
+            .map(|king| {
+                self.attackers
+                    .iter()
+                    .map(|attacker| (*attacker, king))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct AllValidMoves {
     _0: HashMap<Entity, Vec<Move>>,
+    colour: Option<PieceColour>,
 }
 
 impl AllValidMoves {
+    /// the moves of the player whose turn it is, which are the only ones that get calculated
+    pub fn for_colour(colour: PieceColour) -> Self {
+        Self {
+            _0: HashMap::default(),
+            colour: Some(colour),
+        }
+    }
+
+    /// whose moves these are, if they've been calculated rather than built up by hand
+    pub fn colour(&self) -> Option<PieceColour> {
+        self.colour
+    }
+
+    /// only the current player's pieces have moves calculated, so any other piece has no moves
     pub fn get(&self, piece_id: Entity) -> &Vec<Move> {
-        self._0
-            .get(&piece_id)
-            .expect("all pieces should have moves calculated")
+        static NO_MOVES: Vec<Move> = Vec::new();
+
+        self._0.get(&piece_id).unwrap_or(&NO_MOVES)
     }
 
     pub fn insert(&mut self, piece_id: Entity, moves: Vec<Move>) {
@@ -676,6 +1338,41 @@ impl AllValidMoves {
         self.get(piece_id).iter().any(|m| m.target_square == square)
     }
 
+    /// whether the piece can make exactly this move, including what kind of move it is
+    /// (e.g. a King moving onto a Rook's square is only legal as a castling move with that Rook)
+    pub fn is_legal(&self, piece_id: Entity, move_: &Move) -> bool {
+        self.get(piece_id).contains(move_)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &[Move])> + '_ {
+        self._0
+            .iter()
+            .map(|(entity, moves)| (*entity, moves.as_slice()))
+    }
+
+    /// how many legal moves each piece has
+    pub fn move_counts(&self) -> HashMap<Entity, usize> {
+        self._0
+            .iter()
+            .map(|(entity, moves)| (*entity, moves.len()))
+            .collect()
+    }
+
+    pub fn total_move_count(&self) -> usize {
+        self._0.values().map(Vec::len).sum()
+    }
+
+    /// the only move the current player can make, if they have exactly one legal move
+    pub fn forced_move(&self) -> Option<(Entity, Move)> {
+        if self.total_move_count() != 1 {
+            return None;
+        }
+
+        self._0
+            .iter()
+            .find_map(|(entity, moves)| moves.first().map(|move_| (*entity, *move_)))
+    }
+
     pub fn clear(&mut self) {
         self._0.iter_mut().for_each(|(_, moves)| moves.clear())
     }
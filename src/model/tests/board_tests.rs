@@ -1,4 +1,11 @@
-use super::{BoardState, Piece, PieceColour, PieceKind};
+use super::{
+    apply_to_board, can_possibly_win, en_passant_square, material, next_square_contents,
+    overlapping_squares, BoardState, CastlingData, LastPawnDoubleStep, Move, Piece, PieceColour,
+    PieceKind, PositionKey, SpecialMoveData, Square,
+};
+use bevy::prelude::World;
+use std::collections::HashSet;
+use std::hash::{BuildHasherDefault, Hasher};
 
 #[test]
 fn board_state_for_default_board() {
@@ -55,4 +62,381 @@ fn board_state_for_default_board() {
         .chain([Some(PieceColour::Black); 16].into_iter())
         .collect::<Vec<_>>();
     assert_eq!(BoardState::from(&pieces[..]).squares(), &expected);
-}
\ No newline at end of file
+}
+
+#[test]
+fn standard_board_state_should_match_the_default_board() {
+    let expected = [Some(PieceColour::White); 16]
+        .into_iter()
+        .chain([None; 32].into_iter())
+        .chain([Some(PieceColour::Black); 16].into_iter())
+        .collect::<Vec<_>>();
+    assert_eq!(BoardState::standard().squares(), &expected);
+}
+
+#[test]
+fn the_starting_position_should_have_sixteen_pieces_per_player() {
+    let pieces = Piece::starting_position();
+
+    assert_eq!(pieces.len(), 32);
+    assert_eq!(
+        pieces
+            .iter()
+            .filter(|piece| piece.colour == PieceColour::White)
+            .count(),
+        16
+    );
+    assert!(pieces
+        .iter()
+        .any(|piece| piece.kind == PieceKind::King && piece.square == (0, 4).into()));
+    assert!(pieces
+        .iter()
+        .any(|piece| piece.kind == PieceKind::Queen && piece.square == (7, 3).into()));
+}
+
+#[test]
+fn the_starting_position_should_not_have_any_overlapping_pieces() {
+    assert!(overlapping_squares(&Piece::starting_position()).is_empty());
+}
+
+#[test]
+fn two_pieces_on_the_same_square_should_be_flagged() {
+    let pieces = [
+        Piece::white(PieceKind::King, (0, 4).into()),
+        Piece::black(PieceKind::King, (7, 4).into()),
+        Piece::white(PieceKind::Rook, (3, 3).into()),
+        Piece::black(PieceKind::Knight, (3, 3).into()),
+        Piece::white(PieceKind::Pawn, (1, 0).into()),
+        Piece::black(PieceKind::Pawn, (3, 3).into()),
+        Piece::black(PieceKind::Pawn, (1, 0).into()),
+    ];
+
+    assert_eq!(
+        overlapping_squares(&pieces),
+        vec![Square::new(3, 3), Square::new(1, 0)]
+    );
+}
+
+fn assert_occupancy_after_move(before: &[Piece], from: Square, move_: Move, after: &[Piece]) {
+    let board = apply_to_board(&BoardState::from(before), from, move_);
+
+    assert_eq!(board.squares(), BoardState::from(after).squares());
+}
+
+#[test]
+fn applying_a_standard_move_should_move_the_piece() {
+    assert_occupancy_after_move(
+        &[Piece::white(PieceKind::Knight, (0, 6).into())],
+        (0, 6).into(),
+        Move::standard((2, 5).into()),
+        &[Piece::white(PieceKind::Knight, (2, 5).into())],
+    );
+}
+
+#[test]
+fn applying_a_capture_should_replace_the_captured_piece() {
+    assert_occupancy_after_move(
+        &[
+            Piece::white(PieceKind::Bishop, (0, 2).into()),
+            Piece::black(PieceKind::Knight, (5, 7).into()),
+        ],
+        (0, 2).into(),
+        Move::standard((5, 7).into()),
+        &[Piece::white(PieceKind::Bishop, (5, 7).into())],
+    );
+}
+
+#[test]
+fn applying_a_pawn_double_step_should_move_the_pawn_two_squares() {
+    assert_occupancy_after_move(
+        &[Piece::black(PieceKind::Pawn, (6, 4).into())],
+        (6, 4).into(),
+        Move::pawn_double_step((4, 4).into()),
+        &[Piece::black(PieceKind::Pawn, (4, 4).into())],
+    );
+}
+
+#[test]
+fn applying_en_passant_should_remove_the_pawn_beside_the_moving_pawn() {
+    let mut world = World::new();
+
+    assert_occupancy_after_move(
+        &[
+            Piece::white(PieceKind::Pawn, (4, 3).into()),
+            Piece::black(PieceKind::Pawn, (4, 4).into()),
+        ],
+        (4, 3).into(),
+        Move::en_passant((5, 4).into(), world.spawn().id()),
+        &[Piece::white(PieceKind::Pawn, (5, 4).into())],
+    );
+}
+
+#[test]
+fn applying_castling_should_move_the_rook_as_well_as_the_king() {
+    let mut world = World::new();
+    let king = Piece::white(PieceKind::King, (0, 4).into());
+    let kingside_rook = Piece::white(PieceKind::Rook, (0, 7).into());
+    let queenside_rook = Piece::white(PieceKind::Rook, (0, 0).into());
+
+    assert_occupancy_after_move(
+        &[king, kingside_rook, queenside_rook],
+        king.square,
+        Move::kingside_castle(kingside_rook.square, world.spawn().id(), kingside_rook),
+        &[
+            Piece::white(PieceKind::King, (0, 6).into()),
+            Piece::white(PieceKind::Rook, (0, 5).into()),
+            queenside_rook,
+        ],
+    );
+
+    assert_occupancy_after_move(
+        &[king, kingside_rook, queenside_rook],
+        king.square,
+        Move::queenside_castle(queenside_rook.square, world.spawn().id(), queenside_rook),
+        &[
+            Piece::white(PieceKind::King, (0, 2).into()),
+            Piece::white(PieceKind::Rook, (0, 3).into()),
+            kingside_rook,
+        ],
+    );
+}
+
+#[test]
+fn applying_a_promotion_should_move_the_pawn_onto_the_final_rank() {
+    assert_occupancy_after_move(
+        &[
+            Piece::white(PieceKind::Pawn, (6, 0).into()),
+            Piece::black(PieceKind::Rook, (7, 1).into()),
+        ],
+        (6, 0).into(),
+        Move::standard((7, 1).into()),
+        &[Piece::white(PieceKind::Queen, (7, 1).into())],
+    );
+}
+
+#[test]
+fn the_starting_position_should_have_equal_material() {
+    let pieces = Piece::starting_position();
+
+    assert_eq!(material(&pieces, PieceColour::White), 39);
+    assert_eq!(material(&pieces, PieceColour::Black), 39);
+}
+
+#[test]
+fn a_promoted_second_queen_should_count_towards_material() {
+    let pieces = [
+        Piece::white(PieceKind::King, (0, 4).into()),
+        Piece::white(PieceKind::Queen, (0, 3).into()),
+        Piece::white(PieceKind::Queen, (7, 0).into()),
+        Piece::black(PieceKind::King, (7, 4).into()),
+        Piece::black(PieceKind::Rook, (7, 7).into()),
+    ];
+
+    assert_eq!(material(&pieces, PieceColour::White), 18);
+    assert_eq!(material(&pieces, PieceColour::Black), 5);
+}
+
+#[test]
+fn a_lone_king_cannot_possibly_win() {
+    let pieces = [
+        Piece::white(PieceKind::King, (0, 4).into()),
+        Piece::black(PieceKind::King, (7, 4).into()),
+        Piece::black(PieceKind::Queen, (7, 3).into()),
+    ];
+
+    assert!(!can_possibly_win(PieceColour::White, &pieces));
+    assert!(can_possibly_win(PieceColour::Black, &pieces));
+}
+
+#[test]
+fn two_knights_can_possibly_win_even_though_they_cannot_force_it() {
+    let pieces = [
+        Piece::white(PieceKind::King, (0, 4).into()),
+        Piece::white(PieceKind::Knight, (0, 1).into()),
+        Piece::white(PieceKind::Knight, (0, 6).into()),
+        Piece::black(PieceKind::King, (7, 4).into()),
+    ];
+
+    assert!(can_possibly_win(PieceColour::White, &pieces));
+    assert!(!can_possibly_win(PieceColour::Black, &pieces));
+}
+
+#[test]
+fn a_single_minor_piece_can_only_possibly_win_if_the_opponent_has_a_piece_to_block_with() {
+    let white_king = Piece::white(PieceKind::King, (0, 4).into());
+    let black_king = Piece::black(PieceKind::King, (7, 4).into());
+    let knight = Piece::white(PieceKind::Knight, (0, 1).into());
+
+    assert!(!can_possibly_win(
+        PieceColour::White,
+        &[white_king, black_king, knight]
+    ));
+    assert!(can_possibly_win(
+        PieceColour::White,
+        &[
+            white_king,
+            black_king,
+            knight,
+            Piece::black(PieceKind::Rook, (7, 0).into())
+        ]
+    ));
+}
+
+#[test]
+fn bishops_all_on_the_same_colour_squares_cannot_possibly_win() {
+    let white_king = Piece::white(PieceKind::King, (0, 4).into());
+    let black_king = Piece::black(PieceKind::King, (7, 4).into());
+    // c1 and f8 are both dark squares, and c8 is a light one
+    let same_colour = [
+        white_king,
+        black_king,
+        Piece::white(PieceKind::Bishop, (0, 2).into()),
+        Piece::black(PieceKind::Bishop, (7, 5).into()),
+    ];
+    let opposite_colour = [
+        white_king,
+        black_king,
+        Piece::white(PieceKind::Bishop, (0, 2).into()),
+        Piece::black(PieceKind::Bishop, (7, 2).into()),
+    ];
+
+    assert!(!can_possibly_win(PieceColour::White, &same_colour));
+    assert!(!can_possibly_win(PieceColour::Black, &same_colour));
+    assert!(can_possibly_win(PieceColour::White, &opposite_colour));
+    assert!(can_possibly_win(PieceColour::Black, &opposite_colour));
+}
+
+#[test]
+fn the_starting_position_should_keep_every_castling_right() {
+    let special_move_data = SpecialMoveData::from_position(&Piece::starting_position());
+
+    PieceColour::all().into_iter().for_each(|colour| {
+        let castling_data = special_move_data.castling_data(colour);
+        assert!(!castling_data.king_moved);
+        assert!(!castling_data.kingside_rook_moved);
+        assert!(!castling_data.queenside_rook_moved);
+    });
+}
+
+#[test]
+fn a_set_up_position_should_lose_the_castling_rights_of_displaced_kings_and_rooks() {
+    let pieces = [
+        Piece::white(PieceKind::King, (0, 4).into()),
+        Piece::white(PieceKind::Rook, (0, 7).into()),
+        // on the right square, but the wrong colour
+        Piece::black(PieceKind::Rook, (0, 0).into()),
+        Piece::black(PieceKind::King, (7, 0).into()),
+        Piece::black(PieceKind::Rook, (7, 7).into()),
+    ];
+
+    let white = CastlingData::from_position(PieceColour::White, &pieces);
+    assert!(!white.king_moved);
+    assert!(!white.kingside_rook_moved);
+    assert!(white.queenside_rook_moved);
+
+    let black = CastlingData::from_position(PieceColour::Black, &pieces);
+    assert!(black.king_moved);
+    assert!(!black.kingside_rook_moved);
+    assert!(black.queenside_rook_moved);
+}
+
+/// hashes every position the same, so only comparing them can tell them apart
+#[derive(Default)]
+struct CollidingHasher;
+
+impl Hasher for CollidingHasher {
+    fn finish(&self) -> u64 {
+        0
+    }
+
+    fn write(&mut self, _: &[u8]) {}
+}
+
+#[test]
+fn different_positions_with_the_same_hash_should_not_be_repetitions() {
+    let mut positions = HashSet::<PositionKey, BuildHasherDefault<CollidingHasher>>::default();
+    let pieces = Piece::starting_position();
+    let mut king_moved = SpecialMoveData::default();
+    king_moved.white_castling_data.king_moved = true;
+
+    assert!(positions.insert(PositionKey::new(
+        &pieces,
+        PieceColour::White,
+        &SpecialMoveData::default()
+    )));
+    assert!(positions.insert(PositionKey::new(
+        &pieces,
+        PieceColour::Black,
+        &SpecialMoveData::default()
+    )));
+    assert!(positions.insert(PositionKey::new(&pieces, PieceColour::White, &king_moved)));
+
+    // the same position with its pieces listed in a different order is a repetition
+    let mut reordered = pieces.clone();
+    reordered.reverse();
+    assert!(!positions.insert(PositionKey::new(
+        &reordered,
+        PieceColour::White,
+        &SpecialMoveData::default()
+    )));
+    assert_eq!(positions.len(), 3);
+}
+
+#[test]
+fn a_double_step_that_cannot_be_taken_en_passant_should_not_change_the_position() {
+    let mut world = World::new();
+    let pawn = world.spawn().id();
+    let mut double_step = SpecialMoveData::default();
+    double_step.last_pawn_double_step = Some(LastPawnDoubleStep {
+        pawn_id: pawn,
+        square: Square::new(3, 4),
+    });
+
+    // 1. e4, with no Black pawn beside it
+    let mut pieces = Piece::starting_position();
+    pieces.retain(|piece| piece.square != Square::new(1, 4));
+    pieces.push(Piece::white(PieceKind::Pawn, Square::new(3, 4)));
+    assert_eq!(
+        en_passant_square(&pieces, PieceColour::Black, &double_step),
+        None
+    );
+    assert_eq!(
+        PositionKey::new(&pieces, PieceColour::Black, &double_step),
+        PositionKey::new(&pieces, PieceColour::Black, &SpecialMoveData::default())
+    );
+
+    // a Black pawn on d4 could take the e-pawn on e3
+    pieces.retain(|piece| piece.square != Square::new(6, 3));
+    pieces.push(Piece::black(PieceKind::Pawn, Square::new(3, 3)));
+    assert_eq!(
+        en_passant_square(&pieces, PieceColour::Black, &double_step),
+        Some(Square::new(2, 4))
+    );
+    assert_ne!(
+        PositionKey::new(&pieces, PieceColour::Black, &double_step),
+        PositionKey::new(&pieces, PieceColour::Black, &SpecialMoveData::default())
+    );
+}
+
+#[test]
+fn clicking_a_square_in_the_editor_should_cycle_through_every_piece_and_back_to_empty() {
+    let mut contents = next_square_contents(None);
+    assert_eq!(contents, Some((PieceColour::White, PieceKind::King)));
+
+    let mut seen = Vec::new();
+    while let Some(piece) = contents {
+        assert!(!seen.contains(&piece), "{:?} came round twice", piece);
+        seen.push(piece);
+        contents = next_square_contents(contents);
+    }
+
+    assert_eq!(seen.len(), 12);
+    PieceColour::all().into_iter().for_each(|colour| {
+        PieceKind::all()
+            .into_iter()
+            .for_each(|kind| assert!(seen.contains(&(colour, kind)), "{:?} {:?}", colour, kind));
+    });
+    // White's pieces all come before Black's
+    assert_eq!(seen[5], (PieceColour::White, PieceKind::Pawn));
+    assert_eq!(seen[6], (PieceColour::Black, PieceKind::King));
+}
@@ -0,0 +1,113 @@
+use super::*;
+use bevy::prelude::World;
+
+#[test]
+fn each_move_constructor_should_produce_its_move_kind() {
+    let mut world = World::new();
+    let target_id = world.spawn().id();
+    let rook_id = world.spawn().id();
+    let square = Square::new(0, 6);
+
+    assert_eq!(Move::standard(square).kind, MoveKind::Standard);
+    assert_eq!(
+        Move::pawn_double_step(square).kind,
+        MoveKind::PawnDoubleStep
+    );
+    assert_eq!(
+        Move::en_passant(square, target_id).kind,
+        MoveKind::EnPassant { target_id }
+    );
+
+    let kingside_rook = Piece::white(PieceKind::Rook, Square::new(0, 7));
+    assert_eq!(
+        Move::kingside_castle(square, rook_id, kingside_rook).kind,
+        MoveKind::Castle {
+            rook_id,
+            rook_position: kingside_rook.square,
+            king_target_y: 6,
+            rook_target_y: 5,
+            kingside: true,
+        }
+    );
+
+    let queenside_rook = Piece::white(PieceKind::Rook, Square::new(0, 0));
+    assert_eq!(
+        Move::queenside_castle(square, rook_id, queenside_rook).kind,
+        MoveKind::Castle {
+            rook_id,
+            rook_position: queenside_rook.square,
+            king_target_y: 2,
+            rook_target_y: 3,
+            kingside: false,
+        }
+    );
+}
+
+#[test]
+fn move_kinds_should_display_as_their_names() {
+    let mut world = World::new();
+    let rook_id = world.spawn().id();
+    let rook = Piece::white(PieceKind::Rook, Square::new(0, 7));
+
+    assert_eq!(MoveKind::Standard.to_string(), "Standard");
+    assert_eq!(MoveKind::PawnDoubleStep.to_string(), "Pawn double step");
+    assert_eq!(
+        MoveKind::EnPassant {
+            target_id: world.spawn().id()
+        }
+        .to_string(),
+        "En passant"
+    );
+    assert_eq!(
+        Move::kingside_castle(Square::new(0, 7), rook_id, rook)
+            .kind
+            .to_string(),
+        "Kingside castle"
+    );
+    assert_eq!(
+        Move::queenside_castle(Square::new(0, 0), rook_id, rook)
+            .kind
+            .to_string(),
+        "Queenside castle"
+    );
+}
+
+#[test]
+fn only_castling_moves_should_have_a_castling_destination() {
+    let mut world = World::new();
+    let rook_id = world.spawn().id();
+    let rook = Piece::black(PieceKind::Rook, Square::new(7, 0));
+
+    assert_eq!(
+        Move::queenside_castle(rook.square, rook_id, rook).castling_destination(),
+        Some(Square::new(7, 2))
+    );
+    assert_eq!(
+        Move::standard(Square::new(7, 2)).castling_destination(),
+        None
+    );
+}
+
+#[test]
+fn only_moves_onto_an_opponents_piece_or_en_passant_should_be_captures() {
+    let mut world = World::new();
+    let pawn_id = world.spawn().id();
+    let rook_id = world.spawn().id();
+    let rook = Piece::white(PieceKind::Rook, Square::new(0, 7));
+    let board = BoardState::from([
+        Piece::white(PieceKind::King, (0, 4).into()),
+        rook,
+        Piece::white(PieceKind::Pawn, (4, 4).into()),
+        Piece::black(PieceKind::Pawn, (4, 3).into()),
+        Piece::black(PieceKind::Knight, (5, 5).into()),
+    ]);
+
+    assert!(Move::standard((5, 5).into()).is_capture(&board, PieceColour::White));
+    assert!(Move::en_passant((5, 3).into(), pawn_id).is_capture(&board, PieceColour::White));
+    assert!(!Move::standard((5, 4).into()).is_capture(&board, PieceColour::White));
+    // castling targets the player's own Rook
+    assert!(
+        !Move::kingside_castle(rook.square, rook_id, rook).is_capture(&board, PieceColour::White)
+    );
+    assert!(!Move::standard((5, 5).into()).is_capture(&board, PieceColour::Black));
+}
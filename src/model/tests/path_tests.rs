@@ -0,0 +1,54 @@
+use super::*;
+
+fn rook_path_with_a_blocker() -> PiecePath {
+    let rook = Piece::white(PieceKind::Rook, (0, 0).into());
+    let pieces = [
+        rook,
+        Piece::black(PieceKind::Pawn, (3, 0).into()),
+        Piece::white(PieceKind::Pawn, (5, 0).into()),
+    ];
+
+    rook.valid_moves(&pieces.into())
+        .into_iter()
+        .find(|path| path.contains((1, 0).into()))
+        .expect("rook should be able to move up")
+}
+
+#[test]
+fn the_blocking_piece_should_be_the_first_obstruction_in_the_path() {
+    let path = rook_path_with_a_blocker();
+
+    assert_eq!(
+        path.blocking_piece(),
+        Some(Obstruction {
+            square: (3, 0).into(),
+            colour: PieceColour::Black,
+        })
+    );
+}
+
+#[test]
+fn an_unobstructed_path_should_not_have_a_blocking_piece() {
+    let rook = Piece::white(PieceKind::Rook, (0, 0).into());
+    let paths = rook.valid_moves(&[rook].into());
+
+    assert!(paths.iter().all(|path| path.blocking_piece().is_none()));
+}
+
+#[test]
+fn pseudo_legal_squares_should_include_every_square_in_the_path_regardless_of_blockers() {
+    let path = rook_path_with_a_blocker();
+
+    assert_eq!(
+        path.pseudo_legal_squares().collect::<Vec<_>>(),
+        (1..8).map(|rank| Square::new(rank, 0)).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        path.legal_path_vec(),
+        vec![
+            Move::standard((1, 0).into()),
+            Move::standard((2, 0).into()),
+            Move::standard((3, 0).into()),
+        ]
+    );
+}
@@ -0,0 +1,58 @@
+use super::*;
+
+#[test]
+fn all_kinds_should_contain_each_of_the_six_kinds_exactly_once() {
+    let kinds = PieceKind::all();
+
+    assert_eq!(kinds.len(), 6);
+    [
+        PieceKind::King,
+        PieceKind::Queen,
+        PieceKind::Bishop,
+        PieceKind::Knight,
+        PieceKind::Rook,
+        PieceKind::Pawn,
+    ]
+    .into_iter()
+    .for_each(|kind| {
+        assert_eq!(kinds.iter().filter(|other| **other == kind).count(), 1);
+    });
+}
+
+#[test]
+fn all_colours_should_contain_each_colour_exactly_once() {
+    assert_eq!(PieceColour::all(), [PieceColour::White, PieceColour::Black]);
+}
+
+#[test]
+fn piece_values_should_match_the_standard_material_values() {
+    assert_eq!(
+        PieceKind::all().map(|kind| kind.value()),
+        [0, 9, 3, 3, 5, 1]
+    );
+}
+
+#[test]
+fn fen_chars_should_be_upper_case_for_white_and_lower_case_for_black() {
+    assert_eq!(
+        PieceKind::all().map(|kind| kind.fen_char(PieceColour::White)),
+        ['K', 'Q', 'B', 'N', 'R', 'P']
+    );
+    assert_eq!(
+        PieceKind::all().map(|kind| kind.fen_char(PieceColour::Black)),
+        ['k', 'q', 'b', 'n', 'r', 'p']
+    );
+}
+
+#[test]
+fn algebraic_letters_should_match_standard_notation() {
+    assert_eq!(
+        PieceKind::all().map(|kind| kind.algebraic_letter()),
+        ["K", "Q", "B", "N", "R", ""]
+    );
+}
+
+#[test]
+fn a_pawn_should_not_have_an_algebraic_letter() {
+    assert!(PieceKind::Pawn.algebraic_letter().is_empty());
+}
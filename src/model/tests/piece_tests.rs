@@ -1514,4 +1514,133 @@ mod valid_moves_of_a_rook {
             vec![Move::standard((1, 0).into())]
         );
     }
+
+    #[test]
+    fn should_not_be_able_to_move_past_the_edge_of_a_smaller_board() {
+        let rook = rook(1, 1);
+        let valid_moves = rook.valid_moves_on_board(&[rook].into(), 6);
+        assert_eq!(
+            valid_moves,
+            vec![
+                single_move_path((0, 1), rook.colour),
+                PiecePath::new(
+                    vec![
+                        unblocked_move((2, 1)),
+                        unblocked_move((3, 1)),
+                        unblocked_move((4, 1)),
+                        unblocked_move((5, 1)),
+                    ],
+                    rook.colour
+                ),
+                PiecePath::new(
+                    vec![
+                        unblocked_move((1, 2)),
+                        unblocked_move((1, 3)),
+                        unblocked_move((1, 4)),
+                        unblocked_move((1, 5)),
+                    ],
+                    rook.colour
+                ),
+                single_move_path((1, 0), rook.colour),
+            ]
+        );
+    }
+}
+
+#[test]
+fn a_pawn_one_square_from_promotion_should_only_be_able_to_advance_one_square() {
+    let white_pawn = Piece::white(PieceKind::Pawn, (6, 3).into());
+    let black_pawn = Piece::black(PieceKind::Pawn, (1, 3).into());
+    let board = [white_pawn, black_pawn].into();
+
+    assert_eq!(
+        white_pawn.valid_moves(&board),
+        vec![single_move_path((7, 3), PieceColour::White)]
+    );
+    assert_eq!(
+        black_pawn.valid_moves(&board),
+        vec![single_move_path((0, 3), PieceColour::Black)]
+    );
+}
+
+mod attacks {
+    use super::*;
+
+    #[test]
+    fn a_pawn_should_attack_both_diagonals_but_only_move_forwards() {
+        let pawn = Piece::white(PieceKind::Pawn, (3, 3).into());
+        let board = [pawn].into();
+
+        assert_eq!(
+            pawn.attacks(&board),
+            vec![Square::new(4, 2), Square::new(4, 4)]
+        );
+        assert_eq!(
+            pawn.valid_moves(&board),
+            vec![single_move_path((4, 3), PieceColour::White)]
+        );
+    }
+
+    #[test]
+    fn a_pawn_on_the_edge_of_the_board_should_only_attack_one_square() {
+        let pawn = Piece::black(PieceKind::Pawn, (6, 0).into());
+
+        assert_eq!(pawn.attacks(&[pawn].into()), vec![Square::new(5, 1)]);
+    }
+
+    #[test]
+    fn a_rook_should_attack_up_to_and_including_the_first_piece_in_each_direction() {
+        let rook = Piece::white(PieceKind::Rook, (0, 0).into());
+        let own_pawn = Piece::white(PieceKind::Pawn, (1, 0).into());
+        let enemy_knight = Piece::black(PieceKind::Knight, (0, 2).into());
+        let board = [rook, own_pawn, enemy_knight].into();
+
+        assert_eq!(
+            rook.attacks(&board),
+            vec![Square::new(1, 0), Square::new(0, 1), Square::new(0, 2)]
+        );
+    }
+}
+
+#[test]
+fn duplicate_pieces_should_be_removed_by_a_set() {
+    let pieces = [
+        Piece::white(PieceKind::Pawn, (1, 0).into()),
+        Piece::black(PieceKind::Pawn, (6, 0).into()),
+        Piece::white(PieceKind::Pawn, (1, 0).into()),
+        Piece::black(PieceKind::King, (7, 4).into()),
+        Piece::black(PieceKind::Pawn, (6, 0).into()),
+    ];
+
+    let unique = pieces
+        .iter()
+        .copied()
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(unique.len(), 3);
+    assert!(unique.contains(&Piece::black(PieceKind::King, (7, 4).into())));
+}
+
+#[test]
+fn sorting_pieces_should_order_them_by_colour_then_kind_then_square() {
+    let mut pieces = vec![
+        Piece::black(PieceKind::Pawn, (6, 0).into()),
+        Piece::white(PieceKind::Pawn, (1, 1).into()),
+        Piece::white(PieceKind::Pawn, (1, 0).into()),
+        Piece::black(PieceKind::King, (7, 4).into()),
+        Piece::white(PieceKind::Knight, (0, 6).into()),
+        Piece::white(PieceKind::Pawn, (2, 0).into()),
+    ];
+    pieces.sort();
+
+    assert_eq!(
+        pieces,
+        vec![
+            Piece::white(PieceKind::Knight, (0, 6).into()),
+            Piece::white(PieceKind::Pawn, (1, 0).into()),
+            Piece::white(PieceKind::Pawn, (1, 1).into()),
+            Piece::white(PieceKind::Pawn, (2, 0).into()),
+            Piece::black(PieceKind::King, (7, 4).into()),
+            Piece::black(PieceKind::Pawn, (6, 0).into()),
+        ]
+    );
 }
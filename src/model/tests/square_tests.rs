@@ -0,0 +1,206 @@
+use super::*;
+
+#[test]
+fn square_translations_should_be_centred_on_the_board() {
+    let scale = BoardScale::default();
+
+    assert_eq!(
+        Square::new(0, 0).to_translation(scale),
+        Vec3::new(-3.5, 0.0, -3.5)
+    );
+    assert_eq!(
+        Square::new(7, 7).to_translation(scale),
+        Vec3::new(3.5, 0.0, 3.5)
+    );
+}
+
+#[test]
+fn doubling_the_board_scale_should_double_square_translations() {
+    let square = Square::new(2, 6);
+    let translation = square.to_translation(BoardScale(1.0));
+
+    assert_eq!(square.to_translation(BoardScale(2.0)), translation * 2.0);
+}
+
+#[test]
+fn converting_a_square_to_a_translation_and_back_should_give_the_same_square() {
+    let scale = BoardScale(2.5);
+
+    Square::all().for_each(|square| {
+        assert_eq!(
+            Square::from_translation(square.to_translation(scale), scale),
+            square
+        );
+    });
+}
+
+#[test]
+fn all_squares_should_cover_the_whole_board_exactly_once() {
+    let mut squares = Square::all()
+        .map(|square| (square.rank, square.file))
+        .collect::<Vec<_>>();
+    assert_eq!(squares.len(), 64);
+    assert_eq!(squares.first(), Some(&(0, 0)));
+    assert_eq!(squares.last(), Some(&(7, 7)));
+
+    squares.sort_unstable();
+    squares.dedup();
+    assert_eq!(squares.len(), 64);
+    assert!(squares
+        .iter()
+        .all(|(rank, file)| *rank < BOARD_SIZE && *file < BOARD_SIZE));
+}
+
+#[test]
+fn a_square_in_the_middle_of_the_board_should_have_eight_neighbours() {
+    let neighbours = Square::new(3, 3).neighbours().collect::<Vec<_>>();
+
+    assert_eq!(
+        neighbours,
+        vec![
+            Square::new(2, 2),
+            Square::new(2, 3),
+            Square::new(2, 4),
+            Square::new(3, 2),
+            Square::new(3, 4),
+            Square::new(4, 2),
+            Square::new(4, 3),
+            Square::new(4, 4),
+        ]
+    );
+}
+
+#[test]
+fn a_corner_square_should_only_have_neighbours_on_the_board() {
+    assert_eq!(
+        Square::new(0, 0).neighbours().collect::<Vec<_>>(),
+        vec![Square::new(0, 1), Square::new(1, 0), Square::new(1, 1)]
+    );
+    assert_eq!(
+        Square::new(7, 7).neighbours().collect::<Vec<_>>(),
+        vec![Square::new(6, 6), Square::new(6, 7), Square::new(7, 6)]
+    );
+}
+
+#[test]
+fn an_edge_square_should_have_five_neighbours() {
+    assert_eq!(Square::new(0, 4).neighbours().count(), 5);
+    assert_eq!(Square::new(4, 7).neighbours().count(), 5);
+}
+
+#[test]
+fn squares_should_alternate_between_dark_and_light() {
+    assert!(Square::new(0, 0).is_dark());
+    assert!(!Square::new(0, 1).is_dark());
+    assert!(!Square::new(1, 0).is_dark());
+    assert!(Square::new(7, 7).is_dark());
+    assert!(!Square::new(0, 7).is_dark());
+}
+
+#[test]
+fn chebyshev_distance_should_be_the_number_of_king_moves_between_two_squares() {
+    assert_eq!(Square::new(0, 0).chebyshev_distance(Square::new(0, 0)), 0);
+    assert_eq!(Square::new(0, 0).chebyshev_distance(Square::new(1, 1)), 1);
+    assert_eq!(Square::new(0, 0).chebyshev_distance(Square::new(7, 7)), 7);
+    assert_eq!(Square::new(2, 5).chebyshev_distance(Square::new(6, 3)), 4);
+    assert_eq!(Square::new(6, 3).chebyshev_distance(Square::new(2, 5)), 4);
+}
+
+#[test]
+fn points_on_the_board_should_be_inside_the_square_they_are_over() {
+    let scale = BoardScale::default();
+
+    assert_eq!(
+        Square::at_board_point(Vec3::new(-3.5, 0.0, -3.5), scale),
+        Some(Square::new(0, 0))
+    );
+    assert_eq!(
+        Square::at_board_point(Vec3::new(0.2, 0.0, -0.7), scale),
+        Some(Square::new(3, 4))
+    );
+    // just either side of the boundary between d4 and e4
+    assert_eq!(
+        Square::at_board_point(Vec3::new(-0.01, 0.0, -0.5), scale),
+        Some(Square::new(3, 3))
+    );
+    assert_eq!(
+        Square::at_board_point(Vec3::new(0.01, 0.0, -0.5), scale),
+        Some(Square::new(3, 4))
+    );
+    assert_eq!(
+        Square::at_board_point(Vec3::new(3.99, 0.0, 3.99), scale),
+        Some(Square::new(7, 7))
+    );
+}
+
+#[test]
+fn points_off_the_edge_of_the_board_should_not_be_inside_any_square() {
+    let scale = BoardScale::default();
+
+    [
+        Vec3::new(-4.01, 0.0, 0.0),
+        Vec3::new(4.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -4.01),
+        Vec3::new(0.0, 0.0, 4.0),
+        Vec3::new(-40.0, 0.0, 40.0),
+    ]
+    .into_iter()
+    .for_each(|point| assert_eq!(Square::at_board_point(point, scale), None, "{}", point));
+}
+
+#[test]
+fn points_should_be_mapped_to_squares_at_any_board_scale() {
+    let scale = BoardScale(2.0);
+
+    assert_eq!(
+        Square::at_board_point(Vec3::new(-7.9, 0.0, 7.9), scale),
+        Some(Square::new(7, 0))
+    );
+    assert_eq!(
+        Square::at_board_point(Vec3::new(1.9, 0.0, 2.1), scale),
+        Some(Square::new(5, 4))
+    );
+    assert_eq!(
+        Square::at_board_point(Vec3::new(8.1, 0.0, 0.0), scale),
+        None
+    );
+}
+
+#[test]
+fn a_ray_pointing_at_the_board_should_meet_it_at_y_0() {
+    let origin = Vec3::new(0.0, 10.0, -10.0);
+
+    let point = board_plane_intersection(origin, Vec3::new(0.1, -1.0, 1.0)).unwrap();
+    assert!((point - Vec3::new(1.0, 0.0, 0.0)).length() < 0.0001);
+
+    assert_eq!(
+        board_plane_intersection(origin, Vec3::new(0.0, 1.0, 1.0)),
+        None
+    );
+    assert_eq!(
+        board_plane_intersection(origin, Vec3::new(0.0, 0.0, 1.0)),
+        None
+    );
+}
+
+#[test]
+fn squares_should_be_named_by_file_then_rank() {
+    assert_eq!(Square::new(0, 0).to_string(), "a1");
+    assert_eq!(Square::new(3, 4).to_string(), "e4");
+    assert_eq!(Square::new(7, 7).to_string(), "h8");
+    assert_eq!(Square::from_algebraic("e4"), Some(Square::new(3, 4)));
+}
+
+#[test]
+fn every_square_should_round_trip_through_its_name() {
+    Square::all().for_each(|square| {
+        assert_eq!(Square::from_algebraic(&square.to_string()), Some(square));
+    });
+}
+
+#[test]
+fn anything_other_than_a_square_name_should_not_be_read_as_a_square() {
+    ["", "e", "i4", "e0", "e9", "E4", "4e", "e44", "é4"]
+        .into_iter()
+        .for_each(|name| assert_eq!(Square::from_algebraic(name), None, "{}", name));
+}
@@ -0,0 +1,295 @@
+use super::*;
+use bevy::prelude::World;
+
+#[test]
+fn should_find_the_forced_move_when_only_one_piece_has_a_single_legal_move() {
+    let mut world = World::new();
+    let king = world.spawn().id();
+    let pawn = world.spawn().id();
+
+    let mut all_moves = AllValidMoves::default();
+    all_moves.insert(king, vec![Move::standard((7, 3).into())]);
+    all_moves.insert(pawn, vec![]);
+
+    assert_eq!(all_moves.total_move_count(), 1);
+    assert_eq!(
+        all_moves.forced_move(),
+        Some((king, Move::standard((7, 3).into())))
+    );
+}
+
+#[test]
+fn should_not_find_a_forced_move_when_there_is_a_choice_of_moves() {
+    let mut world = World::new();
+    let king = world.spawn().id();
+    let pawn = world.spawn().id();
+
+    let mut all_moves = AllValidMoves::default();
+    all_moves.insert(king, vec![Move::standard((7, 3).into())]);
+    all_moves.insert(pawn, vec![Move::standard((5, 0).into())]);
+
+    assert_eq!(all_moves.total_move_count(), 2);
+    assert_eq!(all_moves.forced_move(), None);
+}
+
+#[test]
+fn should_not_find_a_forced_move_after_the_moves_have_been_cleared() {
+    let mut world = World::new();
+    let king = world.spawn().id();
+
+    let mut all_moves = AllValidMoves::default();
+    all_moves.insert(king, vec![Move::standard((7, 3).into())]);
+    all_moves.clear();
+
+    assert_eq!(all_moves.total_move_count(), 0);
+    assert_eq!(all_moves.forced_move(), None);
+}
+
+#[test]
+fn a_knight_in_the_corner_should_only_have_two_moves() {
+    let mut world = World::new();
+    let king = world.spawn().id();
+    let knight = world.spawn().id();
+
+    let black_king = Piece::black(PieceKind::King, (7, 4).into());
+    let black_knight = Piece::black(PieceKind::Knight, (0, 0).into());
+    let player_pieces = [(king, &black_king), (knight, &black_knight)];
+
+    let mut special_move_data = SpecialMoveData::default();
+    special_move_data.black_castling_data.king_moved = true;
+
+    let all_moves = match crate::moves_calculator::calculate_valid_moves(
+        PieceColour::Black,
+        &special_move_data,
+        &player_pieces,
+        &[],
+        [black_king, black_knight].into(),
+    ) {
+        crate::moves_calculator::CalculatorResult::Ok(all_moves, _) => all_moves,
+        _ => panic!("black should be able to move"),
+    };
+
+    let move_counts = all_moves.move_counts();
+    assert_eq!(move_counts.len(), 2);
+    assert_eq!(move_counts[&knight], 2);
+    assert_eq!(move_counts[&king], 5);
+}
+
+#[test]
+fn only_moves_in_the_calculated_set_should_be_legal() {
+    let mut world = World::new();
+    let king = world.spawn().id();
+    let rook = world.spawn().id();
+    let other_piece = world.spawn().id();
+
+    let castle = Move::kingside_castle(
+        (0, 7).into(),
+        rook,
+        Piece::white(PieceKind::Rook, (0, 7).into()),
+    );
+    let mut all_moves = AllValidMoves::default();
+    all_moves.insert(king, vec![Move::standard((1, 4).into()), castle]);
+
+    assert!(all_moves.is_legal(king, &Move::standard((1, 4).into())));
+    assert!(all_moves.is_legal(king, &castle));
+
+    assert!(!all_moves.is_legal(king, &Move::standard((2, 4).into())));
+    // the right square, but not as a castling move
+    assert!(!all_moves.is_legal(king, &Move::standard((0, 7).into())));
+    assert!(!all_moves.is_legal(other_piece, &Move::standard((1, 4).into())));
+}
+
+#[test]
+fn a_queen_in_the_open_should_have_more_mobility_than_one_boxed_in() {
+    let open = [
+        Piece::white(PieceKind::King, (0, 7).into()),
+        Piece::white(PieceKind::Queen, (3, 3).into()),
+        Piece::white(PieceKind::Pawn, (1, 0).into()),
+        Piece::white(PieceKind::Pawn, (1, 1).into()),
+        Piece::white(PieceKind::Bishop, (0, 1).into()),
+    ];
+    let boxed_in = [
+        Piece::white(PieceKind::King, (0, 7).into()),
+        Piece::white(PieceKind::Queen, (0, 0).into()),
+        Piece::white(PieceKind::Pawn, (1, 0).into()),
+        Piece::white(PieceKind::Pawn, (1, 1).into()),
+        Piece::white(PieceKind::Bishop, (0, 1).into()),
+    ];
+
+    let legal_moves = |pieces: &[Piece]| {
+        let mut world = World::new();
+        let pieces = pieces
+            .iter()
+            .map(|piece| (world.spawn().id(), piece))
+            .collect::<Vec<_>>();
+        match crate::moves_calculator::calculate_valid_moves(
+            PieceColour::White,
+            &SpecialMoveData::default(),
+            &pieces,
+            &[],
+            pieces.iter().map(|(_, piece)| *piece).collect(),
+        ) {
+            crate::moves_calculator::CalculatorResult::Ok(all_moves, _) => all_moves,
+            _ => panic!("white should be able to move"),
+        }
+    };
+
+    // the King, Pawns, and Bishop have 13 moves between them in both positions
+    assert_eq!(mobility(&legal_moves(&open), PieceColour::White), 13 + 25);
+    assert_eq!(mobility(&legal_moves(&boxed_in), PieceColour::White), 13);
+    // only the player whose turn it is has their legal moves calculated
+    assert_eq!(mobility(&legal_moves(&open), PieceColour::Black), 0);
+
+    assert_eq!(pseudo_legal_mobility(&open, PieceColour::White), 13 + 25);
+    assert_eq!(pseudo_legal_mobility(&boxed_in, PieceColour::White), 13);
+    assert_eq!(pseudo_legal_mobility(&boxed_in, PieceColour::Black), 0);
+}
+
+#[test]
+fn legal_mobility_should_not_count_moves_that_leave_the_king_in_check() {
+    let mut world = World::new();
+    let king = world.spawn().id();
+    let rook = world.spawn().id();
+    let black_queen = world.spawn().id();
+
+    let white_king = Piece::white(PieceKind::King, (0, 4).into());
+    let white_rook = Piece::white(PieceKind::Rook, (1, 4).into());
+    let queen = Piece::black(PieceKind::Queen, (7, 4).into());
+    let pieces = [white_king, white_rook, queen];
+
+    let all_moves = match crate::moves_calculator::calculate_valid_moves(
+        PieceColour::White,
+        &SpecialMoveData::default(),
+        &[(king, &white_king), (rook, &white_rook)],
+        &[(black_queen, &queen)],
+        pieces.iter().collect(),
+    ) {
+        crate::moves_calculator::CalculatorResult::Ok(all_moves, _) => all_moves,
+        _ => panic!("white should be able to move"),
+    };
+
+    // the pinned Rook can only move along the e-file, and the King can't step onto it
+    assert_eq!(mobility(&all_moves, PieceColour::White), 4 + 6);
+    assert_eq!(pseudo_legal_mobility(&pieces, PieceColour::White), 4 + 13);
+}
+
+#[test]
+fn every_piece_with_a_clear_line_to_a_square_should_be_one_of_its_attackers() {
+    let mut world = World::new();
+    let mut spawn = |piece: Piece| (world.spawn().id(), piece);
+
+    let white_pieces = [
+        spawn(Piece::white(PieceKind::King, (0, 6).into())),
+        spawn(Piece::white(PieceKind::Rook, (0, 4).into())),
+        spawn(Piece::white(PieceKind::Queen, (1, 4).into())),
+        spawn(Piece::white(PieceKind::Bishop, (1, 1).into())),
+        spawn(Piece::white(PieceKind::Knight, (2, 5).into())),
+        spawn(Piece::white(PieceKind::Knight, (3, 2).into())),
+        spawn(Piece::white(PieceKind::Pawn, (3, 3).into())),
+        spawn(Piece::white(PieceKind::Pawn, (3, 5).into())),
+    ];
+    let black_pieces = [
+        spawn(Piece::black(PieceKind::King, (7, 6).into())),
+        spawn(Piece::black(PieceKind::Queen, (7, 7).into())),
+        spawn(Piece::black(PieceKind::Rook, (7, 4).into())),
+        spawn(Piece::black(PieceKind::Knight, (6, 3).into())),
+        spawn(Piece::black(PieceKind::Pawn, (5, 3).into())),
+        spawn(Piece::black(PieceKind::Pawn, (5, 5).into())),
+        spawn(Piece::black(PieceKind::Pawn, (4, 4).into())),
+    ];
+    let pieces = [&white_pieces[..], &black_pieces[..]].concat();
+    let e5 = Square::new(4, 4);
+
+    // the Rook is behind the Queen, and the Bishop is behind the d4 Pawn
+    let white_attackers = [2, 4, 5, 6, 7].map(|index| white_pieces[index].0);
+    assert_eq!(
+        attackers_of(e5, PieceColour::White, &pieces),
+        white_attackers
+    );
+
+    // the Queen is behind the f6 Pawn, and the e5 Pawn doesn't attack its own square
+    let black_defenders = [2, 3, 4, 5].map(|index| black_pieces[index].0);
+    assert_eq!(
+        attackers_of(e5, PieceColour::Black, &pieces),
+        black_defenders
+    );
+}
+
+#[test]
+fn an_empty_square_out_of_reach_should_have_no_attackers() {
+    let mut world = World::new();
+    let pieces = Piece::starting_position()
+        .into_iter()
+        .map(|piece| (world.spawn().id(), piece))
+        .collect::<Vec<_>>();
+
+    assert!(attackers_of(Square::new(3, 4), PieceColour::White, &pieces).is_empty());
+    assert!(attackers_of(Square::new(4, 4), PieceColour::Black, &pieces).is_empty());
+    assert_eq!(
+        attackers_of(Square::new(2, 5), PieceColour::White, &pieces).len(),
+        3
+    );
+}
+
+fn castling_moves(player_pieces: &[(Entity, &Piece)]) -> Vec<Move> {
+    let board_state = player_pieces.iter().map(|(_, piece)| *piece).collect();
+    match crate::moves_calculator::calculate_valid_moves(
+        PieceColour::White,
+        &SpecialMoveData::default(),
+        player_pieces,
+        &[],
+        board_state,
+    ) {
+        crate::moves_calculator::CalculatorResult::Ok(all_moves, _) => all_moves
+            .iter()
+            .flat_map(|(_, moves)| moves.iter().copied())
+            .filter(|move_| matches!(move_.kind, MoveKind::Castle { .. }))
+            .collect(),
+        _ => panic!("white should be able to move"),
+    }
+}
+
+#[test]
+fn castling_should_not_be_offered_without_the_king_and_rook_on_their_starting_squares() {
+    let mut world = World::new();
+    let king = world.spawn().id();
+    let rook = world.spawn().id();
+
+    // the castling data says nothing has moved, but the position disagrees
+    let cornered_king = Piece::white(PieceKind::King, (0, 0).into());
+    assert!(castling_moves(&[(king, &cornered_king)]).is_empty());
+
+    let lone_king = Piece::white(PieceKind::King, (0, 4).into());
+    assert!(castling_moves(&[(king, &lone_king)]).is_empty());
+
+    let kingside_rook = Piece::white(PieceKind::Rook, (0, 7).into());
+    assert_eq!(
+        castling_moves(&[(king, &lone_king), (rook, &kingside_rook)]),
+        vec![Move::kingside_castle((0, 7).into(), rook, kingside_rook)]
+    );
+
+    let displaced_king = Piece::white(PieceKind::King, (0, 3).into());
+    assert!(castling_moves(&[(king, &displaced_king), (rook, &kingside_rook)]).is_empty());
+}
+
+#[test]
+fn the_position_status_should_not_trust_default_castling_data_in_a_set_up_position() {
+    let mut world = World::new();
+    let pieces = [
+        Piece::black(PieceKind::King, (7, 0).into()),
+        Piece::white(PieceKind::Queen, (5, 1).into()),
+        Piece::white(PieceKind::King, (0, 4).into()),
+    ]
+    .map(|piece| (world.spawn().id(), piece));
+    let board = pieces.iter().map(|(_, piece)| piece).collect();
+
+    assert_eq!(
+        position_status(
+            &board,
+            &pieces,
+            PieceColour::Black,
+            &SpecialMoveData::default()
+        ),
+        PositionStatus::Stalemate
+    );
+}
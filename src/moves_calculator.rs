@@ -1,14 +1,14 @@
 use crate::model::{
-    AllValidMoves, BoardState, Move, MoveKind, Piece, PieceColour, PieceKind, PiecePath,
-    PotentialMove, SpecialMoveData, Square,
+    AllValidMoves, BoardState, CheckState, Move, MoveKind, Piece, PieceColour, PieceKind,
+    PiecePath, PotentialMove, SpecialMoveData, Square, BOARD_SIZE, KING_STARTING_FILE,
 };
 use bevy::prelude::Entity;
 use bevy::utils::HashMap;
 
 pub enum CalculatorResult {
     Stalemate,
-    Checkmate,
-    Ok(AllValidMoves),
+    Checkmate(CheckState),
+    Ok(AllValidMoves, CheckState),
 }
 
 pub fn calculate_valid_moves(
@@ -18,11 +18,12 @@ pub fn calculate_valid_moves(
     opposite_pieces: &[(Entity, &Piece)],
     board_state: BoardState,
 ) -> CalculatorResult {
-    let (king_entity, king) = player_pieces
+    let Some((king_entity, king)) = player_pieces
         .iter()
         .find(|(_, piece)| piece.kind == PieceKind::King)
-        .copied()
-        .expect("there should always be two kings");
+        .copied() else {
+        return calculate_moves_without_king(turn, player_pieces, &board_state);
+    };
 
     let calculator = MoveCalculator {
         turn,
@@ -37,6 +38,30 @@ pub fn calculate_valid_moves(
     calculator.calculate_valid_moves()
 }
 
+/// a custom position might not have a King: there's nothing to protect, so check and pins don't apply,
+/// and every move a piece could make is allowed (apart from en passant, which is rare enough not to bother with)
+fn calculate_moves_without_king(
+    turn: PieceColour,
+    player_pieces: &[(Entity, &Piece)],
+    board_state: &BoardState,
+) -> CalculatorResult {
+    let mut all_moves = AllValidMoves::for_colour(turn);
+    player_pieces.iter().for_each(|(entity, piece)| {
+        let moves = piece
+            .valid_moves(board_state)
+            .iter()
+            .flat_map(PiecePath::legal_path)
+            .collect();
+        let _ = all_moves.insert(*entity, moves);
+    });
+
+    if all_moves.total_move_count() == 0 {
+        CalculatorResult::Stalemate
+    } else {
+        CalculatorResult::Ok(all_moves, CheckState::default())
+    }
+}
+
 struct MoveCalculator<'game> {
     turn: PieceColour,
     special_move_data: &'game SpecialMoveData,
@@ -84,6 +109,9 @@ impl AllPotentialMoves {
 }
 
 impl<'game> MoveCalculator<'game> {
+    /// every piece's paths are generated exactly once; checks and pins are then found by looking
+    /// along the opposite pieces' paths towards the king, so candidate moves are never played out
+    /// and re-checked
     fn calculate_valid_moves(self) -> CalculatorResult {
         let mut all_potential_moves = AllPotentialMoves::new();
 
@@ -96,34 +124,44 @@ impl<'game> MoveCalculator<'game> {
             .for_each(|(entity, piece)| {
                 let mut valid_moves = piece.valid_moves(&self.board_state);
 
-                if let Some((left, _)) = &en_passant_left {
-                    if entity == *left {
-                        valid_moves.push(en_passant_left.take().unwrap().1);
-                    }
-                } else if let Some((right, _)) = &en_passant_right {
-                    if entity == *right {
-                        valid_moves.push(en_passant_right.take().unwrap().1);
-                    }
-                };
+                // a pawn that double steps between two enemy pawns can be taken by either of them,
+                // whichever order they're found in
+                [&mut en_passant_left, &mut en_passant_right]
+                    .into_iter()
+                    .for_each(|en_passant| {
+                        if en_passant
+                            .as_ref()
+                            .map_or(false, |(pawn, _)| *pawn == entity)
+                        {
+                            valid_moves.push(en_passant.take().unwrap().1);
+                        }
+                    });
 
                 all_potential_moves.insert(entity, valid_moves);
             });
 
         let pieces_attacking_king = self.pieces_attacking_king(&all_potential_moves);
+        let check_state = CheckState {
+            king_square: Some(self.king_square),
+            attackers: pieces_attacking_king
+                .iter()
+                .map(|(_, piece, _)| piece.square)
+                .collect(),
+        };
 
         if !pieces_attacking_king.is_empty() {
             let counter_moves =
                 self.calculate_check_counter_moves(pieces_attacking_king, &all_potential_moves);
 
             if counter_moves.iter().all(|(_, moves)| moves.is_empty()) {
-                CalculatorResult::Checkmate
+                CalculatorResult::Checkmate(check_state)
             } else {
-                let mut all_moves = AllValidMoves::default();
+                let mut all_moves = AllValidMoves::for_colour(self.turn);
                 counter_moves.into_iter().for_each(|(entity, moves)| {
                     let _ = all_moves.insert(entity, moves);
                 });
 
-                CalculatorResult::Ok(all_moves)
+                CalculatorResult::Ok(all_moves, check_state)
             }
         } else {
             let safe_player_moves = self.calculate_safe_player_moves(&all_potential_moves);
@@ -138,14 +176,14 @@ impl<'game> MoveCalculator<'game> {
                 return CalculatorResult::Stalemate;
             }
 
-            let mut all_moves = AllValidMoves::default();
+            let mut all_moves = AllValidMoves::for_colour(self.turn);
 
             let _ = all_moves.insert(self.king_entity, safe_king_moves);
             safe_player_moves.into_iter().for_each(|(entity, moves)| {
                 let _ = all_moves.insert(entity, moves);
             });
 
-            CalculatorResult::Ok(all_moves)
+            CalculatorResult::Ok(all_moves, check_state)
         }
     }
 
@@ -154,7 +192,7 @@ impl<'game> MoveCalculator<'game> {
             let find_pawn_in_column = |offset: i8| {
                 let expected_y = pawn_double_step.square.file as i8 + offset;
                 #[allow(clippy::manual_range_contains)]
-                if expected_y < 0 || expected_y > 7 {
+                if expected_y < 0 || expected_y >= BOARD_SIZE as i8 {
                     return None;
                 };
 
@@ -197,25 +235,22 @@ impl<'game> MoveCalculator<'game> {
             .flat_map(PiecePath::legal_path)
             .filter(|king_move| {
                 let attacked = self.opposite_pieces.iter().any(|(entity, piece)| {
-                    // check that taking the piece on the square doesn't put the king in check
-                    if self.board_state.get(king_move.target_square).is_some() {
+                    if piece.kind == PieceKind::Pawn {
+                        // pawn behaviour is very different to other pieces, and it's easier to handle
+                        // the interactions here than try to get PotentialMove/PiecePath to handle it properly
+                        // note: this also covers a pawn defending the piece the king would take,
+                        // which a pawn's potential moves never include
+                        piece
+                            .attacks(&self.board_state)
+                            .contains(&king_move.target_square)
+                    } else if self.board_state.get(king_move.target_square).is_some() {
+                        // check that taking the piece on the square doesn't put the king in check
                         potential_moves.get(*entity).iter().any(|path| {
                             path.obstructions()
                                 .first()
                                 .map(|obstruction| obstruction.square == king_move.target_square)
                                 .unwrap_or(false)
                         })
-                    } else if piece.kind == PieceKind::Pawn {
-                        // pawn behaviour is very different to other pieces, and it's easier to handle
-                        // the interactions here than try to get PotentialMove/PiecePath to handle it properly
-                        let will_attack_king = |move_: &Option<PotentialMove>| {
-                            let Some(potential_move) = move_ else { return false };
-                            potential_move.target_square == king_move.target_square
-                        };
-                        let pawn_moves = piece.pawn_moves(&self.board_state, true);
-
-                        will_attack_king(&pawn_moves.attack_left)
-                            || will_attack_king(&pawn_moves.attack_right)
                     } else {
                         // check that the square isn't directly attacked, or that the king isn't currently blocking that square from being attacked
                         let Some(path) = potential_moves.potential_path_to(*entity, king_move.target_square) else { return false };
@@ -347,6 +382,9 @@ impl<'game> MoveCalculator<'game> {
             .collect()
     }
 
+    /// castling is only illegal if the King is in check, or would pass through or land on an
+    /// attacked square - the Rook itself can be attacked, and so can the square next to it on the
+    /// queenside. The Rook can't be pinned to the King either, as nothing can get behind it
     fn calculate_castling_moves(&self, potential_moves: &AllPotentialMoves) -> Moves {
         let king_does_not_pass_through_attacked_square = |dir: i8| {
             let first_move = Square::new(
@@ -368,45 +406,41 @@ impl<'game> MoveCalculator<'game> {
 
         let mut moves = vec![];
         let castling_data = self.special_move_data.castling_data(self.turn);
+        let back_rank = self.turn.starting_back_rank();
+
+        // a position that was set up rather than played might not have the King or Rooks where
+        // the castling data expects them
+        if castling_data.king_moved
+            || self.king_square != Square::new(back_rank, KING_STARTING_FILE)
+        {
+            return moves;
+        }
+        let rook_on = |file: u8| {
+            self.player_pieces.iter().find(|(_, piece)| {
+                piece.kind == PieceKind::Rook && piece.square == Square::new(back_rank, file)
+            })
+        };
 
-        if !castling_data.king_moved {
-            if !castling_data.queenside_rook_moved {
-                let passed_through = Square::new(self.king_square.rank, self.king_square.file - 3);
+        if !castling_data.queenside_rook_moved {
+            if let Some((rook_id, rook)) = rook_on(0) {
+                // only the Rook passes through this square, so it has to be empty but doesn't have to be safe
+                let passed_through = Square::new(back_rank, 1);
 
                 if king_does_not_pass_through_attacked_square(-1)
                     && self.board_state.get(passed_through).is_none()
                 {
-                    let (rook_id, rook) = self
-                        .player_pieces
-                        .iter()
-                        .find(|(_, piece)| {
-                            piece.square.rank == self.king_square.rank && piece.square.file == 0
-                        })
-                        .expect("queenside castling without a rook");
-                    moves.push(Move::queenside_castle(
-                        (self.king_square.rank, 0).into(),
-                        *rook_id,
-                        **rook,
-                    ));
+                    moves.push(Move::queenside_castle(rook.square, *rook_id, **rook));
                 }
             }
+        }
 
-            if !castling_data.kingside_rook_moved && king_does_not_pass_through_attacked_square(1) {
-                let (rook_id, rook) = self
-                    .player_pieces
-                    .iter()
-                    .find(|(_, piece)| {
-                        piece.square.rank == self.king_square.rank && piece.square.file == 7
-                    })
-                    .expect("kingside castling without a rook");
-
-                moves.push(Move::kingside_castle(
-                    (self.king_square.rank, 7).into(),
-                    *rook_id,
-                    **rook,
-                ));
+        if !castling_data.kingside_rook_moved {
+            if let Some((rook_id, rook)) = rook_on(BOARD_SIZE - 1) {
+                if king_does_not_pass_through_attacked_square(1) {
+                    moves.push(Move::kingside_castle(rook.square, *rook_id, **rook));
+                }
             }
-        };
+        }
 
         moves
     }
@@ -0,0 +1,620 @@
+use crate::model::{
+    AllValidMoves, Move, MoveKind, Piece, PieceColour, PieceKind, SpecialMoveData, Square,
+    BOARD_SIZE,
+};
+use bevy::prelude::Entity;
+
+/// lists every legal move in UCI-style coordinate notation, e.g. "e2e4", or "e7e8q" for a promotion
+///
+/// castling is written as the King moving two squares, e.g. "e1g1"
+pub fn legal_moves_uci(moves: &AllValidMoves, pieces: &[(Entity, Piece)]) -> Vec<String> {
+    moves
+        .iter()
+        .filter_map(|(entity, moves)| {
+            let (_, piece) = pieces.iter().find(|(id, _)| *id == entity)?;
+            Some(moves.iter().flat_map(move |move_| uci_moves(piece, move_)))
+        })
+        .flatten()
+        .collect()
+}
+
+fn uci_moves(piece: &Piece, move_: &Move) -> Vec<String> {
+    let coordinates = move_coordinates(piece, move_);
+
+    if piece.kind == PieceKind::Pawn && move_.target_square.rank == piece.colour.final_rank() {
+        PROMOTIONS
+            .into_iter()
+            // UCI always writes the promoted piece in lower case, whichever side is promoting
+            .map(|promotion| format!("{}{}", coordinates, promotion.fen_char(PieceColour::Black)))
+            .collect()
+    } else {
+        vec![coordinates]
+    }
+}
+
+/// writes a single move in coordinate notation, e.g. "e2e4", without any promotion suffix
+pub fn move_coordinates(piece: &Piece, move_: &Move) -> String {
+    format!("{}{}", piece.square, destination(move_))
+}
+
+/// the pieces a pawn can be promoted to, in the order UCI-style move lists give them
+const PROMOTIONS: [PieceKind; 4] = [
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+];
+
+/// reads a move in coordinate notation, e.g. "e2e4", or "e7e8q" for a promotion, into the square
+/// moved from, the square moved to, and the piece promoted to
+///
+/// castling is written as the King moving two squares, and the promotion is in lower case, the same
+/// as `legal_moves_uci` writes it
+pub fn parse_move(text: &str) -> Option<(Square, Square, Option<PieceKind>)> {
+    if !text.is_ascii() || !(4..=5).contains(&text.len()) {
+        return None;
+    }
+
+    let promotion = match text[4..].chars().next() {
+        Some(letter) => Some(
+            PROMOTIONS
+                .into_iter()
+                .find(|kind| kind.fen_char(PieceColour::Black) == letter)?,
+        ),
+        None => None,
+    };
+
+    Some((
+        Square::from_algebraic(&text[..2])?,
+        Square::from_algebraic(&text[2..4])?,
+        promotion,
+    ))
+}
+
+/// writes a single move in standard algebraic notation, e.g. "Nf3", "exd5" or "O-O"
+///
+/// `rivals` are the squares of the other pieces that could make the same move (see `rival_squares`),
+/// which are needed to tell the moves apart, e.g. "Rad1" rather than "Rd1"
+///
+/// check, checkmate, and promotions aren't marked, as none of them are known at the point the move is made
+pub fn move_algebraic(piece: &Piece, move_: &Move, captures: bool, rivals: &[Square]) -> String {
+    if let MoveKind::Castle { kingside, .. } = move_.kind {
+        return if kingside { "O-O" } else { "O-O-O" }.to_string();
+    }
+
+    let target = destination(move_).to_string();
+    if piece.kind == PieceKind::Pawn {
+        // a pawn capture already names the file it came from, and a pawn push can only come from one square
+        return if captures {
+            format!("{}x{}", piece.square.file_name(), target)
+        } else {
+            target
+        };
+    }
+
+    format!(
+        "{}{}{}{}",
+        piece.kind.algebraic_letter(),
+        disambiguation(piece.square, rivals),
+        if captures { "x" } else { "" },
+        target
+    )
+}
+
+/// the squares of the other pieces of the same kind and colour that could also move to `target`,
+/// e.g. a second Queen after a promotion
+pub fn rival_squares(
+    moves: &AllValidMoves,
+    pieces: &[(Entity, Piece)],
+    piece_id: Entity,
+    target: Square,
+) -> Vec<Square> {
+    let Some((_, piece)) = pieces.iter().find(|(id, _)| *id == piece_id) else {
+        return Vec::new();
+    };
+
+    pieces
+        .iter()
+        .filter(|(id, other)| {
+            *id != piece_id
+                && other.kind == piece.kind
+                && other.colour == piece.colour
+                && moves.contains(*id, target)
+        })
+        .map(|(_, other)| other.square)
+        .collect()
+}
+
+/// the file if that's enough to identify the piece, otherwise the rank, otherwise the whole square
+fn disambiguation(square: Square, rivals: &[Square]) -> String {
+    if rivals.is_empty() {
+        String::new()
+    } else if rivals.iter().all(|rival| rival.file != square.file) {
+        square.file_name().to_string()
+    } else if rivals.iter().all(|rival| rival.rank != square.rank) {
+        (square.rank + 1).to_string()
+    } else {
+        square.to_string()
+    }
+}
+
+/// castling is stored as the King moving onto the Rook's square, but is written as the King's actual destination
+fn destination(move_: &Move) -> Square {
+    move_.castling_destination().unwrap_or(move_.target_square)
+}
+
+/// describes the position in Forsyth-Edwards Notation
+///
+/// the half move clock isn't tracked, so it's always written as 0
+pub fn to_fen(
+    pieces: &[Piece],
+    turn: PieceColour,
+    special_move_data: &SpecialMoveData,
+    fullmove_number: u32,
+) -> String {
+    let placement = ranks_from_the_top()
+        .map(|rank| {
+            let mut row = String::new();
+            let mut empty_squares = 0;
+
+            rank.iter()
+                .for_each(|square| match piece_at(pieces, *square) {
+                    Some(piece) => {
+                        if empty_squares > 0 {
+                            row.push_str(&empty_squares.to_string());
+                            empty_squares = 0;
+                        }
+                        row.push(piece.kind.fen_char(piece.colour));
+                    }
+                    None => empty_squares += 1,
+                });
+
+            if empty_squares > 0 {
+                row.push_str(&empty_squares.to_string());
+            }
+            row
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let active_colour = match turn {
+        PieceColour::White => "w",
+        PieceColour::Black => "b",
+    };
+
+    let castling = PieceColour::all()
+        .into_iter()
+        .flat_map(|colour| {
+            let castling_data = special_move_data.castling_data(colour);
+            let kingside = (!castling_data.king_moved && !castling_data.kingside_rook_moved)
+                .then(|| PieceKind::King.fen_char(colour));
+            let queenside = (!castling_data.king_moved && !castling_data.queenside_rook_moved)
+                .then(|| PieceKind::Queen.fen_char(colour));
+
+            [kingside, queenside]
+        })
+        .flatten()
+        .collect::<String>();
+
+    // the square the pawn skipped over, which is behind it from the point of view of the player who moved it
+    let en_passant = special_move_data
+        .last_pawn_double_step
+        .as_ref()
+        .map(|double_step| double_step.skipped_square(turn.opposite()).to_string());
+
+    format!(
+        "{} {} {} {} 0 {}",
+        placement,
+        active_colour,
+        if castling.is_empty() {
+            "-".to_string()
+        } else {
+            castling
+        },
+        en_passant.unwrap_or_else(|| "-".to_string()),
+        fullmove_number
+    )
+}
+
+/// writes the game in Portable Game Notation, with placeholders for the players and event
+///
+/// `moves` are in algebraic notation, alternating White then Black from the start of the game, and
+/// `result` is `None` while the game is still in progress
+pub fn to_pgn(moves: &[String], result: Option<&str>) -> String {
+    let result = result.unwrap_or("*");
+    let tags = [
+        ("Event", "?"),
+        ("Site", "?"),
+        ("Date", "????.??.??"),
+        ("Round", "?"),
+        ("White", "?"),
+        ("Black", "?"),
+        ("Result", result),
+    ]
+    .into_iter()
+    .map(|(name, value)| format!("[{} \"{}\"]\n", name, value))
+    .collect::<String>();
+
+    let movetext = moves
+        .chunks(2)
+        .zip(1..)
+        .map(|(pair, move_number)| format!("{}. {}", move_number, pair.join(" ")))
+        .chain(std::iter::once(result.to_string()))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    format!("{}\n{}\n", tags, movetext)
+}
+
+/// draws the board as text, one line per rank with Black's back rank at the top
+///
+/// pieces are written as their FEN letters, and empty squares as `.`
+pub fn render_ascii(pieces: &[Piece]) -> String {
+    ranks_from_the_top()
+        .map(|rank| {
+            rank.iter()
+                .map(|square| {
+                    piece_at(pieces, *square).map_or('.', |piece| piece.kind.fen_char(piece.colour))
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// each rank's squares from the a-file to the h-file, starting with Black's back rank
+fn ranks_from_the_top() -> impl Iterator<Item = Vec<Square>> {
+    Square::all()
+        .collect::<Vec<_>>()
+        .chunks(BOARD_SIZE as usize)
+        .map(<[Square]>::to_vec)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+}
+
+fn piece_at(pieces: &[Piece], square: Square) -> Option<&Piece> {
+    pieces.iter().find(|piece| piece.square == square)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{BoardState, LastPawnDoubleStep};
+    use crate::moves_calculator::{calculate_valid_moves, CalculatorResult};
+    use bevy::prelude::World;
+
+    fn legal_moves(
+        turn: PieceColour,
+        special_move_data: &SpecialMoveData,
+        pieces: &[Piece],
+    ) -> Vec<String> {
+        let mut world = World::new();
+        let pieces = pieces
+            .iter()
+            .map(|piece| (world.spawn().id(), *piece))
+            .collect::<Vec<_>>();
+
+        let board_state = pieces
+            .iter()
+            .map(|(_, piece)| piece)
+            .collect::<BoardState>();
+        let (player_pieces, opposite_pieces): (Vec<_>, Vec<_>) = pieces
+            .iter()
+            .map(|(entity, piece)| (*entity, piece))
+            .partition(|(_, piece)| piece.colour == turn);
+
+        let CalculatorResult::Ok(all_moves, _) = calculate_valid_moves(
+            turn,
+            special_move_data,
+            &player_pieces,
+            &opposite_pieces,
+            board_state,
+        ) else {
+            panic!("expected some legal moves")
+        };
+
+        let mut moves = legal_moves_uci(&all_moves, &pieces);
+        moves.sort();
+        moves
+    }
+
+    #[test]
+    fn should_list_the_twenty_legal_moves_from_the_starting_position() {
+        let pieces = Piece::starting_position();
+
+        let mut expected = [
+            "a2a3", "a2a4", "b2b3", "b2b4", "c2c3", "c2c4", "d2d3", "d2d4", "e2e3", "e2e4", "f2f3",
+            "f2f4", "g2g3", "g2g4", "h2h3", "h2h4", "b1a3", "b1c3", "g1f3", "g1h3",
+        ]
+        .map(String::from)
+        .to_vec();
+        expected.sort();
+
+        assert_eq!(
+            legal_moves(PieceColour::White, &SpecialMoveData::default(), &pieces),
+            expected
+        );
+    }
+
+    #[test]
+    fn should_write_castling_as_a_two_square_king_move() {
+        let pieces = [
+            Piece::white(PieceKind::King, Square::new(0, 4)),
+            Piece::white(PieceKind::Rook, Square::new(0, 7)),
+            Piece::white(PieceKind::Pawn, Square::new(1, 4)),
+            Piece::white(PieceKind::Pawn, Square::new(1, 5)),
+            Piece::white(PieceKind::Pawn, Square::new(1, 6)),
+            Piece::white(PieceKind::Pawn, Square::new(1, 7)),
+            Piece::white(PieceKind::Pawn, Square::new(1, 3)),
+            Piece::black(PieceKind::King, Square::new(7, 4)),
+        ];
+        let mut special_move_data = SpecialMoveData::default();
+        special_move_data.white_castling_data.queenside_rook_moved = true;
+
+        let moves = legal_moves(PieceColour::White, &special_move_data, &pieces);
+
+        assert!(moves.contains(&"e1g1".to_string()));
+        assert!(!moves.contains(&"e1h1".to_string()));
+    }
+
+    #[test]
+    fn should_list_every_promotion_option_for_a_pawn_reaching_the_final_rank() {
+        let pieces = [
+            Piece::white(PieceKind::King, Square::new(0, 0)),
+            Piece::white(PieceKind::Pawn, Square::new(6, 4)),
+            Piece::black(PieceKind::King, Square::new(7, 0)),
+        ];
+        let mut special_move_data = SpecialMoveData::default();
+        special_move_data.white_castling_data.king_moved = true;
+
+        let moves = legal_moves(PieceColour::White, &special_move_data, &pieces);
+
+        ["e7e8n", "e7e8b", "e7e8r", "e7e8q"]
+            .into_iter()
+            .for_each(|expected| assert!(moves.contains(&expected.to_string()), "{:?}", moves));
+        // and each of them can be read back in
+        moves
+            .iter()
+            .for_each(|move_| assert!(parse_move(move_).is_some(), "{}", move_));
+    }
+
+    #[test]
+    fn should_write_single_moves_in_coordinate_and_algebraic_notation() {
+        let knight = Piece::white(PieceKind::Knight, Square::new(0, 6));
+        let knight_move = Move::standard(Square::new(2, 5));
+        assert_eq!(move_coordinates(&knight, &knight_move), "g1f3");
+        assert_eq!(move_algebraic(&knight, &knight_move, false, &[]), "Nf3");
+        assert_eq!(move_algebraic(&knight, &knight_move, true, &[]), "Nxf3");
+
+        let mut world = World::new();
+        let pawn = Piece::white(PieceKind::Pawn, Square::new(4, 4));
+        let en_passant = Move::en_passant(Square::new(5, 3), world.spawn().id());
+        assert_eq!(move_coordinates(&pawn, &en_passant), "e5d6");
+        assert_eq!(move_algebraic(&pawn, &en_passant, true, &[]), "exd6");
+    }
+
+    #[test]
+    fn should_write_castling_as_the_kings_destination_and_in_castling_notation() {
+        let mut world = World::new();
+        let king = Piece::black(PieceKind::King, Square::new(7, 4));
+        let kingside_rook = Piece::black(PieceKind::Rook, Square::new(7, 7));
+        let queenside_rook = Piece::black(PieceKind::Rook, Square::new(7, 0));
+
+        let kingside =
+            Move::kingside_castle(kingside_rook.square, world.spawn().id(), kingside_rook);
+        assert_eq!(move_coordinates(&king, &kingside), "e8g8");
+        assert_eq!(move_algebraic(&king, &kingside, false, &[]), "O-O");
+
+        let queenside =
+            Move::queenside_castle(queenside_rook.square, world.spawn().id(), queenside_rook);
+        assert_eq!(move_coordinates(&king, &queenside), "e8c8");
+        assert_eq!(move_algebraic(&king, &queenside, false, &[]), "O-O-O");
+    }
+
+    #[test]
+    fn should_disambiguate_between_two_queens_that_can_both_reach_a_square() {
+        let mut world = World::new();
+        let pieces = [
+            Piece::white(PieceKind::King, Square::new(0, 7)),
+            Piece::white(PieceKind::Queen, Square::new(0, 0)),
+            Piece::white(PieceKind::Queen, Square::new(6, 3)),
+            Piece::black(PieceKind::King, Square::new(7, 7)),
+        ]
+        .map(|piece| (world.spawn().id(), piece));
+        let (_, first_queen) = pieces[1];
+        let (second_queen_id, second_queen) = pieces[2];
+
+        let board_state = pieces
+            .iter()
+            .map(|(_, piece)| piece)
+            .collect::<BoardState>();
+        let (player_pieces, opposite_pieces): (Vec<_>, Vec<_>) = pieces
+            .iter()
+            .map(|(entity, piece)| (*entity, piece))
+            .partition(|(_, piece)| piece.colour == PieceColour::White);
+        let mut special_move_data = SpecialMoveData::default();
+        special_move_data.white_castling_data.king_moved = true;
+
+        let CalculatorResult::Ok(all_moves, _) = calculate_valid_moves(
+            PieceColour::White,
+            &special_move_data,
+            &player_pieces,
+            &opposite_pieces,
+            board_state,
+        ) else {
+            panic!("expected some legal moves")
+        };
+
+        // both Queens can reach d4, one along the diagonal and the other down the file
+        let target = Square::new(3, 3);
+        let rivals = rival_squares(&all_moves, &pieces, second_queen_id, target);
+        assert_eq!(rivals, vec![first_queen.square]);
+        assert_eq!(
+            move_algebraic(&second_queen, &Move::standard(target), false, &rivals),
+            "Qdd4"
+        );
+
+        // only the second Queen can reach d8
+        let target = Square::new(7, 3);
+        let rivals = rival_squares(&all_moves, &pieces, second_queen_id, target);
+        assert!(rivals.is_empty());
+        assert_eq!(
+            move_algebraic(&second_queen, &Move::standard(target), false, &rivals),
+            "Qd8"
+        );
+    }
+
+    #[test]
+    fn should_disambiguate_by_rank_and_then_by_square_when_the_file_is_not_enough() {
+        let queen = Piece::black(PieceKind::Queen, Square::new(3, 0));
+        let move_ = Move::standard(Square::new(0, 3));
+
+        assert_eq!(
+            move_algebraic(&queen, &move_, true, &[Square::new(6, 0)]),
+            "Q4xd1"
+        );
+        assert_eq!(
+            move_algebraic(
+                &queen,
+                &move_,
+                false,
+                &[Square::new(6, 0), Square::new(3, 3)]
+            ),
+            "Qa4d1"
+        );
+        assert_eq!(
+            move_algebraic(&queen, &move_, false, &[Square::new(0, 0)]),
+            "Q4d1"
+        );
+    }
+
+    #[test]
+    fn should_render_the_starting_position_as_ascii() {
+        assert_eq!(
+            render_ascii(&Piece::starting_position()),
+            [
+                "rnbqkbnr", "pppppppp", "........", "........", "........", "........", "PPPPPPPP",
+                "RNBQKBNR",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn should_render_empty_squares_between_pieces_as_dots() {
+        let pieces = [
+            Piece::white(PieceKind::King, Square::new(0, 4)),
+            Piece::black(PieceKind::King, Square::new(7, 4)),
+            Piece::black(PieceKind::Pawn, Square::new(4, 3)),
+        ];
+
+        assert_eq!(
+            render_ascii(&pieces),
+            [
+                "....k...", "........", "........", "...p....", "........", "........", "........",
+                "....K...",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn should_export_the_starting_position_as_the_standard_fen() {
+        assert_eq!(
+            to_fen(
+                &Piece::starting_position(),
+                PieceColour::White,
+                &SpecialMoveData::default(),
+                1
+            ),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+    }
+
+    #[test]
+    fn should_export_lost_castling_rights_and_the_en_passant_square() {
+        let mut world = World::new();
+        let pieces = [
+            Piece::white(PieceKind::King, Square::new(0, 4)),
+            Piece::white(PieceKind::Rook, Square::new(0, 7)),
+            Piece::black(PieceKind::King, Square::new(7, 4)),
+            Piece::black(PieceKind::Pawn, Square::new(4, 3)),
+        ];
+
+        let mut special_move_data = SpecialMoveData::default();
+        special_move_data.white_castling_data.queenside_rook_moved = true;
+        special_move_data.black_castling_data.king_moved = true;
+        special_move_data.last_pawn_double_step = Some(LastPawnDoubleStep {
+            pawn_id: world.spawn().id(),
+            square: Square::new(4, 3),
+        });
+
+        assert_eq!(
+            to_fen(&pieces, PieceColour::White, &special_move_data, 12),
+            "4k3/8/8/3p4/8/8/8/4K2R w K d6 0 12"
+        );
+    }
+
+    #[test]
+    fn a_finished_game_should_be_exported_as_pgn_with_its_result() {
+        // Scholar's Mate
+        let moves = ["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7#"].map(String::from);
+
+        assert_eq!(
+            to_pgn(&moves, Some("1-0")),
+            "[Event \"?\"]\n\
+             [Site \"?\"]\n\
+             [Date \"????.??.??\"]\n\
+             [Round \"?\"]\n\
+             [White \"?\"]\n\
+             [Black \"?\"]\n\
+             [Result \"1-0\"]\n\
+             \n\
+             1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0\n"
+        );
+    }
+
+    #[test]
+    fn a_game_in_progress_should_be_exported_with_an_unknown_result() {
+        let pgn = to_pgn(&["d4".to_string(), "d5".to_string()], None);
+
+        assert!(pgn.contains("[Result \"*\"]"));
+        assert!(pgn.ends_with("1. d4 d5 *\n"));
+        assert!(to_pgn(&[], None).ends_with("\n*\n"));
+    }
+
+    #[test]
+    fn coordinate_moves_should_be_parsed_into_their_squares() {
+        assert_eq!(
+            parse_move("e2e4"),
+            Some((Square::new(1, 4), Square::new(3, 4), None))
+        );
+        assert_eq!(
+            parse_move("h8a1"),
+            Some((Square::new(7, 7), Square::new(0, 0), None))
+        );
+        assert_eq!(
+            parse_move("e7e8q"),
+            Some((Square::new(6, 4), Square::new(7, 4), Some(PieceKind::Queen)))
+        );
+        assert_eq!(
+            parse_move("b2a1n"),
+            Some((
+                Square::new(1, 1),
+                Square::new(0, 0),
+                Some(PieceKind::Knight)
+            ))
+        );
+    }
+
+    #[test]
+    fn anything_other_than_a_coordinate_move_should_not_be_parsed() {
+        [
+            "", "e2", "e2e", "i2e4", "e0e4", "e2e9", "E2E4", "Nf3", "é2e4", "e7e8Q", "e7e8k",
+            "e7e8p", "e7e8x", "e7e8qq",
+        ]
+        .into_iter()
+        .for_each(|text| assert_eq!(parse_move(text), None, "{}", text));
+    }
+}
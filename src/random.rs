@@ -0,0 +1,66 @@
+use crate::model::{AllValidMoves, Square};
+use bevy::prelude::Entity;
+
+/// SplitMix64, which is plenty random enough for picking moves and generating positions,
+/// and doesn't need a dependency
+///
+/// the same seed always generates the same sequence of numbers, so anything random can be replayed
+/// from its seed - and unlike xorshift, any seed works, including 0
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// a number from 0 up to, but not including, `bound`
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
+
+/// picks one of the legal moves at random, as the piece to move and the square to move it to,
+/// or `None` if there aren't any
+pub fn random_move(
+    all_valid_moves: &AllValidMoves,
+    rng: &mut SeededRng,
+) -> Option<(Entity, Square)> {
+    let mut moves = all_valid_moves
+        .iter()
+        .flat_map(|(entity, moves)| moves.iter().map(move |move_| (entity, move_.target_square)))
+        .collect::<Vec<_>>();
+    // the moves are stored in a HashMap, so they have to be sorted to make the choice reproducible
+    moves.sort_by_key(|(entity, square)| (entity.id(), square.rank, square.file));
+
+    (!moves.is_empty()).then(|| moves[rng.below(moves.len() as u64) as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_same_seed_should_generate_the_same_numbers_even_if_it_is_zero() {
+        let first = (0..10)
+            .scan(SeededRng::new(0), |rng, _| Some(rng.next_u64()))
+            .collect::<Vec<_>>();
+        let second = (0..10)
+            .scan(SeededRng::new(0), |rng, _| Some(rng.next_u64()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(first, second);
+        assert!(first.iter().all(|number| *number != 0), "{:?}", first);
+        assert_ne!(first[0], SeededRng::new(1).next_u64());
+    }
+}
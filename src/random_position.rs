@@ -0,0 +1,191 @@
+use crate::model::{BoardState, Piece, PieceColour, PieceKind, Square, BOARD_SIZE};
+use crate::random::SeededRng;
+
+/// the most pieces, besides the two Kings, that a generated position can have
+const MAX_EXTRA_PIECES: u64 = 14;
+const MAX_PAWNS: usize = 8;
+
+/// generates random legal positions, e.g. for fuzzing the moves calculator
+///
+/// the same seed always generates the same sequence of positions
+pub struct RandomPositions {
+    rng: SeededRng,
+}
+
+impl RandomPositions {
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            rng: SeededRng::new(seed),
+        }
+    }
+
+    /// a position `to_move` could legally be about to play from: the Kings aren't next to each
+    /// other, no pawns are on either back rank, and the player who just moved isn't left in check
+    pub fn position(&mut self, to_move: PieceColour) -> Vec<Piece> {
+        loop {
+            let pieces = self.candidate();
+            if !is_in_check(&pieces, to_move.opposite()) {
+                return pieces;
+            }
+        }
+    }
+
+    fn candidate(&mut self) -> Vec<Piece> {
+        let white_king = self.square(0..BOARD_SIZE);
+        let black_king = loop {
+            let square = self.square(0..BOARD_SIZE);
+            if square.chebyshev_distance(white_king) > 1 {
+                break square;
+            }
+        };
+
+        let mut pieces = vec![
+            Piece::white(PieceKind::King, white_king),
+            Piece::black(PieceKind::King, black_king),
+        ];
+
+        (0..self.below(MAX_EXTRA_PIECES + 1)).for_each(|_| {
+            let colour = PieceColour::all()[self.below(2) as usize];
+            let pawns = pieces
+                .iter()
+                .filter(|piece| piece.colour == colour && piece.kind == PieceKind::Pawn)
+                .count();
+            let kinds = PieceKind::all()
+                .into_iter()
+                .filter(|kind| *kind != PieceKind::King)
+                .filter(|kind| *kind != PieceKind::Pawn || pawns < MAX_PAWNS)
+                .collect::<Vec<_>>();
+            let kind = kinds[self.below(kinds.len() as u64) as usize];
+
+            let square = loop {
+                let square = if kind == PieceKind::Pawn {
+                    self.square(1..BOARD_SIZE - 1)
+                } else {
+                    self.square(0..BOARD_SIZE)
+                };
+                if pieces.iter().all(|piece| piece.square != square) {
+                    break square;
+                }
+            };
+
+            pieces.push(Piece {
+                colour,
+                kind,
+                square,
+            });
+        });
+
+        pieces
+    }
+
+    fn square(&mut self, ranks: std::ops::Range<u8>) -> Square {
+        let rank = ranks.start + self.below((ranks.end - ranks.start) as u64) as u8;
+        let file = self.below(BOARD_SIZE as u64) as u8;
+
+        Square::new(rank, file)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.rng.below(bound)
+    }
+}
+
+fn is_in_check(pieces: &[Piece], colour: PieceColour) -> bool {
+    let board = pieces.iter().collect::<BoardState>();
+    let Some(king) = pieces.iter().find(|piece| piece.colour == colour && piece.kind == PieceKind::King) else { return false };
+
+    pieces
+        .iter()
+        .filter(|piece| piece.colour != colour)
+        .any(|piece| piece.attacks(&board).contains(&king.square))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::overlapping_squares;
+
+    fn assert_valid(pieces: &[Piece], to_move: PieceColour) {
+        let kings = PieceColour::all().map(|colour| {
+            let kings = pieces
+                .iter()
+                .filter(|piece| piece.colour == colour && piece.kind == PieceKind::King)
+                .collect::<Vec<_>>();
+            assert_eq!(kings.len(), 1, "{:?}", pieces);
+            kings[0].square
+        });
+
+        assert!(kings[0].chebyshev_distance(kings[1]) > 1, "{:?}", pieces);
+        assert!(overlapping_squares(pieces).is_empty(), "{:?}", pieces);
+        assert!(!is_in_check(pieces, to_move.opposite()), "{:?}", pieces);
+
+        pieces.iter().for_each(|piece| {
+            assert!(
+                piece.square.rank < BOARD_SIZE && piece.square.file < BOARD_SIZE,
+                "{:?}",
+                piece
+            );
+            if piece.kind == PieceKind::Pawn {
+                assert!(
+                    piece.square.rank != 0 && piece.square.rank != BOARD_SIZE - 1,
+                    "{:?}",
+                    piece
+                );
+            }
+        });
+
+        PieceColour::all().into_iter().for_each(|colour| {
+            let pawns = pieces
+                .iter()
+                .filter(|piece| piece.colour == colour && piece.kind == PieceKind::Pawn)
+                .count();
+            assert!(pawns <= MAX_PAWNS, "{:?}", pieces);
+        });
+    }
+
+    #[test]
+    fn generated_positions_should_be_legal() {
+        (0..50).for_each(|seed| {
+            let mut positions = RandomPositions::seeded(seed);
+
+            PieceColour::all().into_iter().for_each(|to_move| {
+                let pieces = positions.position(to_move);
+                assert_valid(&pieces, to_move);
+            });
+        });
+    }
+
+    #[test]
+    fn the_same_seed_should_generate_the_same_positions() {
+        let mut first = RandomPositions::seeded(1234);
+        let mut second = RandomPositions::seeded(1234);
+
+        (0..10).for_each(|_| {
+            assert_eq!(
+                first.position(PieceColour::White),
+                second.position(PieceColour::White)
+            );
+        });
+    }
+
+    #[test]
+    fn different_seeds_should_generate_different_positions() {
+        let positions = (0..10)
+            .map(|seed| RandomPositions::seeded(seed).position(PieceColour::White))
+            .collect::<Vec<_>>();
+
+        positions.iter().enumerate().for_each(|(index, position)| {
+            assert!(!positions[..index].contains(position));
+        });
+    }
+
+    #[test]
+    fn a_player_can_be_left_in_check_if_it_is_their_move() {
+        let in_check = (0..200).any(|seed| {
+            let pieces = RandomPositions::seeded(seed).position(PieceColour::White);
+            is_in_check(&pieces, PieceColour::White)
+        });
+
+        assert!(in_check);
+    }
+}
@@ -1,15 +1,22 @@
 use crate::model::{
-    AllValidMoves, LastPawnDoubleStep, MoveKind, Piece, PieceColour, PieceKind, SpecialMoveData,
-    Square,
+    board_plane_intersection, AllValidMoves, BoardScale, BoardState, CheckState, LastMove,
+    LastPawnDoubleStep, Move, MoveKind, Piece, PieceColour, PieceKind, SpecialMoveData, Square,
+    BOARD_SIZE,
 };
 use crate::moves_calculator::CalculatorResult;
-use crate::{easing, moves_calculator};
+use crate::{easing, moves_calculator, notation};
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 use bevy_mod_picking::PickingCamera;
 use std::fmt::Formatter;
+use std::time::{Duration, Instant};
 
+pub mod headless;
+pub mod scripted_moves;
 mod game_set_up;
 use game_set_up::*;
+use scripted_moves::{play_scripted_move, ScriptedMoves};
+pub use game_set_up::Theme;
 
 #[cfg(test)]
 mod tests {
@@ -18,6 +25,11 @@ mod tests {
     mod checking_for_check_tests;
     mod special_move_tests;
     mod piece_movement_tests;
+    mod promotion_tests;
+    mod capture_tests;
+    mod selection_tests;
+    mod set_up_tests;
+    mod headless_tests;
 }
 
 pub struct ChessPlugin;
@@ -29,11 +41,40 @@ impl Plugin for ChessPlugin {
             .init_resource::<PromotedPawn>()
             .init_resource::<PlayerTurn>()
             .init_resource::<AllValidMoves>()
+            .init_resource::<CheckState>()
             .init_resource::<Option<HighlightedSquare>>()
+            .init_resource::<Option<MatingMove>>()
             .init_resource::<SpecialMoveData>()
+            .init_resource::<AnimationSettings>()
+            .init_resource::<MoveEasing>()
+            .init_resource::<BoardScale>()
+            .init_resource::<AutoPlayForcedMoves>()
+            .init_resource::<PreviewMoves>()
+            .init_resource::<HighlightCastling>()
+            .init_resource::<PromotionSettings>()
+            .init_resource::<MoveNumber>()
+            .init_resource::<PerformanceStats>()
+            .init_resource::<GameLog>()
+            .init_resource::<MoveLog>()
+            .init_resource::<ScriptedMoves>()
+            .init_resource::<Board>()
+            .add_event::<PawnPromotable>()
+            .add_event::<PieceCaptured>()
             .add_state(GameState::NewGame)
-            .add_system(highlight_square_on_hover)
+            // runs after everything else, so it catches every piece that moved, was taken, or was promoted this frame
+            .add_system_to_stage(CoreStage::PostUpdate, update_board)
+            .add_system(highlight_square_on_hover.label("highlight_square"))
+            .add_system(grow_promoted_pieces)
+            .add_system(glow_selected_piece)
+            .add_system(sink_captured_pieces)
             .add_system(restart_game)
+            .add_system(toggle_auto_play_forced_moves)
+            .add_system(toggle_preview_moves)
+            .add_system(cycle_move_easing)
+            .add_system(log_fen)
+            .add_system(toggle_game_log)
+            .add_system(log_game)
+            .add_system(log_pgn)
             .add_system_set(
                 SystemSet::on_update(GameState::NewGame).with_system(start_new_game),
             )
@@ -45,11 +86,14 @@ impl Plugin for ChessPlugin {
                             .label("calculate_moves")
                             .after("reset_selected"),
                     )
-                    .with_system(colour_squares.after("calculate_moves")),
+                    .with_system(colour_squares.after("calculate_moves"))
+                    .with_system(mark_check_in_move_log.after("calculate_moves"))
+                    .with_system(play_forced_move.after("calculate_moves")),
             )
             .add_system_set(
                 SystemSet::on_update(GameState::NothingSelected)
-                    .with_system(select_square),
+                    .with_system(select_square)
+                    .with_system(play_scripted_move),
             )
             .add_system_set(
                 SystemSet::on_update(GameState::SquareSelected).with_system(select_piece),
@@ -58,7 +102,12 @@ impl Plugin for ChessPlugin {
                 SystemSet::on_enter(GameState::PieceSelected).with_system(colour_squares),
             )
             .add_system_set(
-                SystemSet::on_update(GameState::PieceSelected).with_system(select_square),
+                SystemSet::on_update(GameState::PieceSelected)
+                    .with_system(select_square)
+                    .with_system(preview_hovered_move.after("highlight_square")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::PieceSelected).with_system(clear_move_previews),
             )
             .add_system_set(
                 SystemSet::on_update(GameState::TargetSquareSelected)
@@ -76,6 +125,9 @@ impl Plugin for ChessPlugin {
                 SystemSet::on_update(GameState::PawnPromotion)
                     .with_system(promote_pawn_at_final_rank),
             );
+
+        #[cfg(feature = "board-validation")]
+        app.add_system(validate_board);
     }
 }
 
@@ -89,24 +141,380 @@ pub struct SelectedPiece(pub Option<Entity>);
 #[derive(Default)]
 pub struct PromotedPawn(pub Option<Entity>);
 
+/// sent when a pawn reaches the final rank, just before the player chooses what to promote it to
+#[derive(Debug, PartialEq)]
+pub struct PawnPromotable {
+    pub entity: Entity,
+    pub square: Square,
+    pub colour: PieceColour,
+}
+
+/// sent when a piece is taken, so the capture can be announced
+#[derive(Debug, PartialEq)]
+pub struct PieceCaptured {
+    pub by: PieceColour,
+    pub kind: PieceKind,
+    /// where the taken piece was, which for en passant isn't where the capturing pawn moves to
+    pub square: Square,
+    pub en_passant: bool,
+}
+
+impl PieceCaptured {
+    /// e.g. "White captures Knight"
+    pub fn message(&self) -> String {
+        format!(
+            "{} captures {:?}{}",
+            self.by,
+            self.kind,
+            if self.en_passant { " en passant" } else { "" }
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct AnimationSettings {
+    /// skips purely cosmetic animations, so the game state updates immediately
+    pub instant: bool,
+}
+
+/// how a moving piece speeds up and slows down on its way across the board
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MoveEasing {
+    Linear,
+    /// starts and finishes gently
+    EaseInOut,
+    /// overshoots the target square and bounces back onto it
+    Bounce,
+}
+
+impl Default for MoveEasing {
+    fn default() -> Self {
+        MoveEasing::EaseInOut
+    }
+}
+
+impl MoveEasing {
+    pub fn all() -> [MoveEasing; 3] {
+        [
+            MoveEasing::Linear,
+            MoveEasing::EaseInOut,
+            MoveEasing::Bounce,
+        ]
+    }
+
+    /// how far along its path a piece is, in 0..1, after the fraction `t` of its move has elapsed
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            MoveEasing::Linear => t,
+            MoveEasing::EaseInOut => ease_xz(t),
+            MoveEasing::Bounce => easing::bounce(t),
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            MoveEasing::Linear => MoveEasing::EaseInOut,
+            MoveEasing::EaseInOut => MoveEasing::Bounce,
+            MoveEasing::Bounce => MoveEasing::Linear,
+        }
+    }
+}
+
+/// scales a newly promoted piece up from nothing, rather than swapping the meshes in a single frame
+#[derive(Component, Default)]
+pub struct PromotionAnimation {
+    pub elapsed: f32,
+}
+
+const PROMOTION_ANIMATION_SECONDS: f32 = 0.3;
+
+/// not really a velocity: a move over `d` squares takes `sqrt(d) / AVERAGE_MOVE_VELOCITY` seconds,
+/// so longer moves are quicker per square
+const AVERAGE_MOVE_VELOCITY: f32 = 5.0;
+
+/// sinks a captured piece into the board, rather than removing it the moment it's taken
+#[derive(Component)]
+pub struct CaptureAnimation {
+    pub start: Vec3,
+    pub elapsed: f32,
+}
+
+impl CaptureAnimation {
+    pub fn new(start: Vec3) -> Self {
+        Self {
+            start,
+            elapsed: 0.0,
+        }
+    }
+}
+
+const CAPTURE_ANIMATION_SECONDS: f32 = 0.4;
+
+/// how the game was won, recorded the moment checkmate is detected
+#[derive(Debug, PartialEq)]
+pub struct MatingMove {
+    /// `None` if the game started in checkmate
+    pub last_move: Option<LastMove>,
+    /// usually just the piece that moved, but a discovered check can mean it's a different piece,
+    /// or more than one
+    pub checking_pieces: Vec<Piece>,
+}
+
+/// looks up what's on each square, without having to search through every square or piece
+#[derive(Default)]
+pub struct Board {
+    squares: HashMap<Square, Entity>,
+    pieces: HashMap<Square, Entity>,
+}
+
+impl Board {
+    /// the entity with the `Square` component, which is what gets clicked on
+    pub fn square_entity(&self, square: Square) -> Option<Entity> {
+        self.squares.get(&square).copied()
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<Entity> {
+        self.pieces.get(&square).copied()
+    }
+}
+
+/// every move played this game in algebraic notation, oldest first, e.g. for exporting it as PGN
+///
+/// the same moves are also kept in coordinate notation, e.g. "e2e4", to replay the game from
+#[derive(Debug, Default)]
+pub struct MoveLog(pub Vec<String>, Vec<String>);
+
+impl MoveLog {
+    /// the moves in coordinate notation separated by spaces, e.g. "e2e4 e7e5 g1f3",
+    /// which `ScriptedMoves` (and so `--moves`) can play back
+    pub fn to_coordinate_string(&self) -> String {
+        self.1.join(" ")
+    }
+
+    fn record_move(&mut self, algebraic: String, coordinates: String) {
+        self.0.push(algebraic);
+        self.1.push(coordinates);
+    }
+
+    /// the promotion is only chosen after the pawn has already moved, so it's added on afterwards
+    fn record_promotion(&mut self, kind: PieceKind) {
+        if let Some(last_move) = self.0.last_mut() {
+            last_move.push('=');
+            last_move.push_str(kind.algebraic_letter());
+        }
+        if let Some(last_move) = self.1.last_mut() {
+            // the same lower case letter whichever side is promoting, as `notation::parse_move` reads it
+            last_move.push(kind.fen_char(PieceColour::Black));
+        }
+    }
+
+    /// check isn't known until the other player's moves have been calculated, so it's also added on
+    /// afterwards - but only once, however many times the moves get recalculated
+    fn record_check(&mut self, checkmate: bool) {
+        if let Some(last_move) = self.0.last_mut() {
+            if !last_move.ends_with(['+', '#']) {
+                last_move.push(if checkmate { '#' } else { '+' });
+            }
+        }
+    }
+}
+
+/// timings for the performance overlay, only recorded while it's enabled
+#[derive(Default)]
+pub struct PerformanceStats {
+    pub enabled: bool,
+    pub last_move_calculation: Option<Duration>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GameLogVerbosity {
+    Off,
+    Moves,
+    /// also logs the FEN of the position after each move
+    MovesAndPositions,
+}
+
+/// a copy-pasteable trace of the game so far, logged at `info` level, for attaching to bug reports
+pub struct GameLog {
+    pub verbosity: GameLogVerbosity,
+    /// every line logged since the log was enabled, oldest first
+    pub lines: Vec<String>,
+    /// the move currently being played out, which is only logged once its turn has finished
+    pending_move: Option<String>,
+    result_logged: bool,
+}
+
+impl Default for GameLog {
+    fn default() -> Self {
+        Self {
+            verbosity: GameLogVerbosity::Off,
+            lines: Vec::new(),
+            pending_move: None,
+            result_logged: false,
+        }
+    }
+}
+
+impl GameLog {
+    fn record_move(
+        &mut self,
+        move_number: u32,
+        piece: &Piece,
+        move_: &Move,
+        captures: bool,
+        rivals: &[Square],
+    ) {
+        if self.verbosity == GameLogVerbosity::Off {
+            return;
+        }
+
+        self.pending_move = Some(format!(
+            "{}{} {} {}",
+            move_number,
+            if piece.colour == PieceColour::White {
+                "."
+            } else {
+                "..."
+            },
+            notation::move_coordinates(piece, move_),
+            notation::move_algebraic(piece, move_, captures, rivals)
+        ));
+    }
+
+    fn log(&mut self, line: String) {
+        info!("{}", line);
+        self.lines.push(line);
+    }
+}
+
+/// when enabled, plays the current player's move for them if they only have one legal move
+#[derive(Default)]
+pub struct AutoPlayForcedMoves(pub bool);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PromotionMode {
+    /// waits for the player to choose which piece to promote to
+    Prompt,
+    AlwaysQueen,
+}
+
+/// each player can choose whether to pick their promotions themselves
+pub struct PromotionSettings {
+    pub white: PromotionMode,
+    pub black: PromotionMode,
+}
+
+impl Default for PromotionSettings {
+    fn default() -> Self {
+        Self {
+            white: PromotionMode::Prompt,
+            black: PromotionMode::Prompt,
+        }
+    }
+}
+
+impl PromotionSettings {
+    pub fn mode(&self, colour: PieceColour) -> PromotionMode {
+        match colour {
+            PieceColour::White => self.white,
+            PieceColour::Black => self.black,
+        }
+    }
+
+    pub fn toggle(&mut self, colour: PieceColour) {
+        let mode = match colour {
+            PieceColour::White => &mut self.white,
+            PieceColour::Black => &mut self.black,
+        };
+
+        *mode = match mode {
+            PromotionMode::Prompt => PromotionMode::AlwaysQueen,
+            PromotionMode::AlwaysQueen => PromotionMode::Prompt,
+        };
+    }
+}
+
+/// when enabled, shows a translucent copy of the selected piece on the hovered square, if it can move there
+#[derive(Default)]
+pub struct PreviewMoves(pub bool);
+
+/// the selected piece's legal target squares, for highlighting
+#[derive(Component, Debug, PartialEq)]
+pub struct ShowMoves(pub Vec<Square>);
+
+/// the squares involved in the selected King's castling moves, so they can be highlighted differently
+/// to its normal moves
+#[derive(Component, Debug, PartialEq)]
+pub struct ShowCastling {
+    /// clicking a castling Rook castles on that side
+    pub rook_squares: Vec<Square>,
+    pub king_destinations: Vec<Square>,
+}
+
+/// marks the selected piece, which glows until it's deselected
+#[derive(Component, Debug)]
+pub struct SelectionGlow;
+
+/// whether castling squares are highlighted differently to the King's normal moves
+pub struct HighlightCastling(pub bool);
+impl Default for HighlightCastling {
+    fn default() -> Self {
+        HighlightCastling(true)
+    }
+}
+
+#[derive(Component)]
+pub struct MovePreview {
+    pub square: Square,
+}
+
 #[derive(Component)]
 pub struct MovePiece {
     pub from: Vec3,
     pub to: Vec3,
     pub elapsed: f32,
+    /// how long the move takes, in seconds
+    pub duration: f32,
+    /// how high the piece is lifted halfway through the move; 0 slides it along the board
+    pub max_height: f32,
+    target: Square,
 }
 
 impl MovePiece {
-    pub fn new(from: Square, to: Square) -> Self {
+    pub fn new(from: Square, to: Square, scale: BoardScale) -> Self {
+        // measured in squares, so pieces move at the same speed regardless of the board scale
+        let distance = (from.to_translation(BoardScale::default())
+            - to.to_translation(BoardScale::default()))
+        .length();
+
         Self {
-            from: from.to_translation(),
-            to: to.to_translation(),
+            from: from.to_translation(scale),
+            to: to.to_translation(scale),
             elapsed: 0.0,
+            duration: distance.sqrt() / AVERAGE_MOVE_VELOCITY,
+            max_height: 0.5 * distance.sqrt() * scale.0,
+            target: to,
         }
     }
 
+    /// the King slides along the back rank while the Rook jumps over it,
+    /// both taking the same time so they land together
+    pub fn castle(
+        king: (Square, Square),
+        rook: (Square, Square),
+        scale: BoardScale,
+    ) -> (Self, Self) {
+        let mut king_move = MovePiece::new(king.0, king.1, scale);
+        let rook_move = MovePiece::new(rook.0, rook.1, scale);
+
+        king_move.duration = rook_move.duration.max(king_move.duration);
+        king_move.max_height = 0.0;
+
+        (king_move, rook_move)
+    }
+
     pub fn target_square(&self) -> Square {
-        Square::from_translation(self.to)
+        self.target
     }
 }
 
@@ -127,9 +535,32 @@ pub enum GameState {
     MovingPiece,
     Checkmate(PieceColour),
     Stalemate(PieceColour),
+    Draw,
     PawnPromotion,
 }
 
+impl GameState {
+    /// no more moves can be made once the game is over, until it's restarted
+    pub fn is_game_over(&self) -> bool {
+        matches!(
+            self,
+            GameState::Checkmate(_) | GameState::Stalemate(_) | GameState::Draw
+        )
+    }
+}
+
+impl GameState {
+    /// the score once the game is over, e.g. "1-0" if White has won
+    pub fn result(&self) -> Option<&'static str> {
+        match self {
+            GameState::Checkmate(PieceColour::White) => Some("0-1"),
+            GameState::Checkmate(PieceColour::Black) => Some("1-0"),
+            GameState::Stalemate(_) | GameState::Draw => Some("1/2-1/2"),
+            _ => None,
+        }
+    }
+}
+
 impl core::fmt::Display for GameState {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -150,6 +581,9 @@ impl core::fmt::Display for GameState {
                     colour
                 )
             }
+            GameState::Draw => {
+                write!(f, "Draw: neither player can win\nPress R to restart")
+            }
             GameState::PawnPromotion => {
                 write!(f, "A pawn can be promoted\nPress Left/Right to cycle between options and Enter to confirm promotion")
             }
@@ -157,6 +591,15 @@ impl core::fmt::Display for GameState {
     }
 }
 
+/// starts at 1, and goes up every time Black moves
+#[derive(Debug)]
+pub struct MoveNumber(pub u32);
+impl Default for MoveNumber {
+    fn default() -> Self {
+        MoveNumber(1)
+    }
+}
+
 #[derive(Debug)]
 pub struct PlayerTurn(pub PieceColour);
 impl Default for PlayerTurn {
@@ -180,9 +623,22 @@ fn colour_squares(
     selected_piece: Res<SelectedPiece>,
     promoted_pawn: Res<PromotedPawn>,
     materials: Res<SquareMaterials>,
+    highlight_castling: Res<HighlightCastling>,
     pieces: Query<(Entity, &Piece)>,
+    shown_moves: Query<(&ShowMoves, Option<&ShowCastling>)>,
     mut squares: Query<(Entity, &Square, &mut Handle<StandardMaterial>)>,
 ) {
+    let captures = selected_piece.0.map_or_else(Vec::new, |piece| {
+        let board = pieces.iter().map(|(_, piece)| piece).collect::<BoardState>();
+
+        valid_moves
+            .get(piece)
+            .iter()
+            .filter(|move_| move_.is_capture(&board, turn.0))
+            .map(|move_| move_.target_square)
+            .collect()
+    });
+
     squares.for_each_mut(|(entity, square, mut material)| {
         if selected_square.0.contains(&entity) {
             *material = materials.selected.clone();
@@ -190,9 +646,23 @@ fn colour_squares(
         };
 
         if let Some(piece) = selected_piece.0 {
-            if valid_moves.contains(piece, *square) {
-                *material = materials.valid_selection.clone();
-                return;
+            if let Ok((targets, castling)) = shown_moves.get(piece) {
+                let castling = highlight_castling.0
+                    && castling.map_or(false, |castling| {
+                        castling.rook_squares.contains(square)
+                            || castling.king_destinations.contains(square)
+                    });
+
+                if castling {
+                    *material = materials.castling.clone();
+                    return;
+                } else if captures.contains(square) {
+                    *material = materials.capture.clone();
+                    return;
+                } else if targets.0.contains(square) {
+                    *material = materials.valid_selection.clone();
+                    return;
+                }
             };
         } else {
             let piece = pieces
@@ -235,17 +705,19 @@ fn highlight_square_on_hover(
     materials: Res<SquareMaterials>,
     mut previous_highlighted_square: ResMut<Option<HighlightedSquare>>,
     pick_state: Query<&PickingCamera>,
+    board_scale: Res<BoardScale>,
+    board: Res<Board>,
     mut squares: Query<&mut Handle<StandardMaterial>, With<Square>>,
 ) {
-    if let Some(previous) = &*previous_highlighted_square {
+    if let Some(previous) = previous_highlighted_square.take() {
         let mut material = squares.get_mut(previous.entity_id).unwrap();
         *material = previous.previous_material.clone();
     };
 
-    if let Some(top_entity) = selected_entity(pick_state) {
-        if let Ok(mut material) = squares.get_mut(top_entity) {
+    if let Some(hovered) = square_under_cursor(pick_state, *board_scale, &board) {
+        if let Ok(mut material) = squares.get_mut(hovered) {
             *previous_highlighted_square = Some(HighlightedSquare {
-                entity_id: top_entity,
+                entity_id: hovered,
                 previous_material: material.clone(),
             });
 
@@ -254,37 +726,264 @@ fn highlight_square_on_hover(
     };
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn calculate_all_moves(
     player_turn: Res<PlayerTurn>,
     special_move_data: Res<SpecialMoveData>,
     mut all_moves: ResMut<AllValidMoves>,
+    mut check_state: ResMut<CheckState>,
+    mut mating_move: ResMut<Option<MatingMove>>,
     mut game_state: ResMut<State<GameState>>,
+    mut performance_stats: ResMut<PerformanceStats>,
     pieces: Query<(Entity, &Piece)>,
 ) {
+    if game_state.current().is_game_over() {
+        return;
+    }
+
+    // neither King can ever be put in check again once they're the only pieces left
+    if pieces.iter().all(|(_, piece)| piece.kind == PieceKind::King) {
+        *check_state = CheckState::default();
+        change_state(&mut game_state, GameState::Draw);
+        return;
+    }
+
+    if !pieces
+        .iter()
+        .any(|(_, piece)| piece.kind == PieceKind::King && piece.colour == player_turn.0)
+    {
+        warn!(
+            "{} has no King, so check and checkmate won't be detected for them",
+            player_turn.0
+        );
+    }
+
     let board_state = pieces.iter().map(|(_, piece)| piece).collect();
     let (player_pieces, opposite_pieces): (Vec<_>, Vec<_>) = pieces
         .iter()
         .partition(|(_, piece)| piece.colour == player_turn.0);
 
-    match moves_calculator::calculate_valid_moves(
+    let started = performance_stats.enabled.then(Instant::now);
+    let result = moves_calculator::calculate_valid_moves(
         player_turn.0,
         &special_move_data,
         player_pieces.as_slice(),
         opposite_pieces.as_slice(),
         board_state,
-    ) {
+    );
+    if let Some(started) = started {
+        performance_stats.last_move_calculation = Some(started.elapsed());
+    }
+
+    match result {
         CalculatorResult::Stalemate => {
-            game_state.set(GameState::Stalemate(player_turn.0)).unwrap();
-        }
-        CalculatorResult::Checkmate => {
-            game_state.set(GameState::Checkmate(player_turn.0)).unwrap();
+            *check_state = CheckState::default();
+            change_state(&mut game_state, GameState::Stalemate(player_turn.0));
         }
-        CalculatorResult::Ok(valid_moves) => {
-            valid_moves.into_iter().for_each(|(k, v)| {
-                all_moves.insert(k, v);
+        CalculatorResult::Checkmate(check) => {
+            let checking_pieces = opposite_pieces
+                .iter()
+                .map(|(_, piece)| *piece)
+                .filter(|piece| check.attackers.contains(&piece.square))
+                .collect();
+            *mating_move = Some(MatingMove {
+                last_move: special_move_data.last_move,
+                checking_pieces,
             });
+            *check_state = check;
+            change_state(&mut game_state, GameState::Checkmate(player_turn.0));
+        }
+        CalculatorResult::Ok(valid_moves, check) => {
+            *check_state = check;
+            *all_moves = valid_moves;
+        }
+    }
+}
+
+/// marks the move that was just played as check or checkmate, using what `calculate_all_moves` found
+fn mark_check_in_move_log(
+    mut move_log: ResMut<MoveLog>,
+    check_state: Res<CheckState>,
+    mating_move: Res<Option<MatingMove>>,
+) {
+    if mating_move.is_some() {
+        move_log.record_check(true);
+    } else if check_state.is_check() {
+        move_log.record_check(false);
+    }
+}
+
+pub fn update_board(
+    mut board: ResMut<Board>,
+    new_squares: Query<(Entity, &Square), Added<Square>>,
+    changed_pieces: Query<(), Changed<Piece>>,
+    removed_pieces: RemovedComponents<Piece>,
+    pieces: Query<(Entity, &Piece)>,
+) {
+    new_squares.for_each(|(entity, square)| {
+        board.squares.insert(*square, entity);
+    });
+
+    if changed_pieces.is_empty() && removed_pieces.iter().next().is_none() {
+        return;
+    }
+
+    board.pieces = pieces
+        .iter()
+        .map(|(entity, piece)| (piece.square, entity))
+        .collect();
+}
+
+/// a transition that's already been queued (e.g. by another system on the same frame)
+/// isn't worth crashing the game over
+fn change_state(game_state: &mut State<GameState>, new_state: GameState) {
+    if let Err(error) = game_state.set(new_state.clone()) {
+        warn!("Couldn't change state to {:?}: {:?}", new_state, error);
+    }
+}
+
+#[cfg(feature = "board-validation")]
+fn validate_board(pieces: Query<&Piece>) {
+    let pieces = pieces.iter().copied().collect::<Vec<_>>();
+    let overlapping = crate::model::overlapping_squares(&pieces);
+
+    if !overlapping.is_empty() {
+        error!("More than one piece on {:?}", overlapping);
+    }
+}
+
+fn toggle_auto_play_forced_moves(
+    input: Res<Input<KeyCode>>,
+    mut auto_play: ResMut<AutoPlayForcedMoves>,
+) {
+    if input.just_pressed(KeyCode::A) {
+        auto_play.0 = !auto_play.0;
+    }
+}
+
+fn play_forced_move(
+    auto_play: Res<AutoPlayForcedMoves>,
+    all_valid_moves: Res<AllValidMoves>,
+    mut selected_piece: ResMut<SelectedPiece>,
+    mut selected_square: ResMut<SelectedSquare>,
+    mut game_state: ResMut<State<GameState>>,
+    board: Res<Board>,
+    scripted_moves: Res<ScriptedMoves>,
+) {
+    // the script already says which move to play, and would go out of step if this played it too
+    if !auto_play.0 || !scripted_moves.is_finished() {
+        return;
+    }
+
+    let Some((piece_id, forced_move)) = all_valid_moves.forced_move() else { return };
+    let Some(square_id) = board.square_entity(forced_move.target_square) else { return };
+
+    selected_piece.0 = Some(piece_id);
+    selected_square.0 = Some(square_id);
+    change_state(&mut game_state, GameState::TargetSquareSelected);
+}
+
+fn cycle_move_easing(input: Res<Input<KeyCode>>, mut move_easing: ResMut<MoveEasing>) {
+    if input.just_pressed(KeyCode::T) {
+        *move_easing = move_easing.next();
+        info!("Move easing: {:?}", *move_easing);
+    }
+}
+
+fn toggle_preview_moves(input: Res<Input<KeyCode>>, mut preview_moves: ResMut<PreviewMoves>) {
+    if input.just_pressed(KeyCode::V) {
+        preview_moves.0 = !preview_moves.0;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn preview_hovered_move(
+    mut commands: Commands,
+    preview_moves: Res<PreviewMoves>,
+    highlighted_square: Res<Option<HighlightedSquare>>,
+    selected_piece: Res<SelectedPiece>,
+    all_valid_moves: Res<AllValidMoves>,
+    board_scale: Res<BoardScale>,
+    meshes: Res<PieceMeshes>,
+    materials: Res<PieceMaterials>,
+    pieces: Query<&Piece>,
+    squares: Query<&Square>,
+    previews: Query<(Entity, &MovePreview)>,
+) {
+    let hovered_square = highlighted_square
+        .as_ref()
+        .and_then(|highlighted| squares.get(highlighted.entity_id).ok());
+    let preview = selected_piece
+        .0
+        .filter(|_| preview_moves.0)
+        .and_then(|piece_id| {
+            let square = *hovered_square?;
+            let piece = pieces.get(piece_id).ok()?;
+
+            all_valid_moves
+                .contains(piece_id, square)
+                .then(|| (piece, square))
+        });
+
+    let mut already_shown = false;
+    previews.for_each(|(entity, existing)| {
+        if preview.map_or(false, |(_, square)| square == existing.square) {
+            already_shown = true;
+        } else {
+            commands.entity(entity).despawn_recursive();
         }
+    });
+
+    let Some((piece, square)) = preview else { return };
+    if already_shown {
+        return;
     }
+
+    commands
+        .spawn_bundle((
+            place_on_square(*board_scale, piece.colour, square),
+            GlobalTransform::identity(),
+        ))
+        .insert(MovePreview { square })
+        .with_children(|parent| {
+            parent.spawn_bundle(PbrBundle {
+                mesh: meshes.get(piece.kind),
+                material: materials.preview(piece.colour),
+                ..Default::default()
+            });
+        });
+}
+
+fn glow_selected_piece(
+    mut commands: Commands,
+    selected_piece: Res<SelectedPiece>,
+    materials: Res<PieceMaterials>,
+    pieces: Query<(Entity, &Piece, &Children, Option<&SelectionGlow>)>,
+    mut piece_meshes: Query<&mut Handle<StandardMaterial>>,
+) {
+    pieces.for_each(|(entity, piece, children, glow)| {
+        let selected = selected_piece.0 == Some(entity);
+        if selected == glow.is_some() {
+            return;
+        }
+
+        let material = if selected {
+            commands.entity(entity).insert(SelectionGlow);
+            materials.selected(piece.colour)
+        } else {
+            commands.entity(entity).remove::<SelectionGlow>();
+            materials.get(piece.colour)
+        };
+        children.iter().for_each(|child| {
+            if let Ok(mut child_material) = piece_meshes.get_mut(*child) {
+                *child_material = material.clone();
+            }
+        });
+    });
+}
+
+fn clear_move_previews(mut commands: Commands, previews: Query<Entity, With<MovePreview>>) {
+    previews.for_each(|entity| commands.entity(entity).despawn_recursive());
 }
 
 fn select_square(
@@ -293,7 +992,8 @@ fn select_square(
     selected_piece: Res<SelectedPiece>,
     mut game_state: ResMut<State<GameState>>,
     pick_state: Query<&PickingCamera>,
-    squares: Query<&Square>,
+    board_scale: Res<BoardScale>,
+    board: Res<Board>,
 ) {
     if !input.just_pressed(MouseButton::Left) {
         return;
@@ -301,37 +1001,43 @@ fn select_square(
 
     input.reset(MouseButton::Left);
 
-    if let Some(square_entity) = selected_entity(pick_state) {
-        if squares.get(square_entity).is_ok() {
-            selected_square.0 = Some(square_entity);
-        }
+    if let Some(square_entity) = square_under_cursor(pick_state, *board_scale, &board) {
+        selected_square.0 = Some(square_entity);
 
         if selected_piece.0.is_some() {
-            game_state.set(GameState::TargetSquareSelected).unwrap();
+            change_state(&mut game_state, GameState::TargetSquareSelected);
         } else {
-            game_state.set(GameState::SquareSelected).unwrap();
+            change_state(&mut game_state, GameState::SquareSelected);
         }
     } else {
         #[allow(clippy::collapsible_else_if)]
         if *game_state.current() != GameState::NothingSelected {
-            game_state.set(GameState::NothingSelected).unwrap();
+            change_state(&mut game_state, GameState::NothingSelected);
         }
     };
 }
 
-fn selected_entity(pick_state: Query<&PickingCamera>) -> Option<Entity> {
-    if let Some((entity, _)) = pick_state.single().intersect_top() {
-        Some(entity)
-    } else {
-        None
-    }
+/// projects the cursor onto the board, rather than picking whichever square's mesh the cursor's ray
+/// hits first, which isn't always the right one close to the edge of a square
+fn square_under_cursor(
+    pick_state: Query<&PickingCamera>,
+    board_scale: BoardScale,
+    board: &Board,
+) -> Option<Entity> {
+    let ray = pick_state.single().ray()?;
+    let point = board_plane_intersection(ray.origin(), ray.direction())?;
+
+    board.square_entity(Square::at_board_point(point, board_scale)?)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn select_piece(
+    mut commands: Commands,
     mut selected_piece: ResMut<SelectedPiece>,
     selected_square: Res<SelectedSquare>,
     mut game_state: ResMut<State<GameState>>,
     turn: Res<PlayerTurn>,
+    all_valid_moves: Res<AllValidMoves>,
     squares: Query<&Square>,
     pieces: Query<(Entity, &Piece)>,
 ) {
@@ -346,9 +1052,26 @@ fn select_piece(
         .find(|(_, piece)| piece.square == *square && piece.colour == turn.0)
         .map(|(entity, _)| {
             selected_piece.0 = Some(entity);
-            game_state.set(GameState::PieceSelected).unwrap();
+            let moves = all_valid_moves.get(entity);
+            let targets = moves.iter().map(|move_| move_.target_square).collect();
+            commands.entity(entity).insert(ShowMoves(targets));
+
+            let (rook_squares, king_destinations): (Vec<_>, Vec<_>) = moves
+                .iter()
+                .filter_map(|move_| {
+                    let destination = move_.castling_destination()?;
+                    Some((move_.target_square, destination))
+                })
+                .unzip();
+            if !rook_squares.is_empty() {
+                commands.entity(entity).insert(ShowCastling {
+                    rook_squares,
+                    king_destinations,
+                });
+            }
+            change_state(&mut game_state, GameState::PieceSelected);
         })
-        .unwrap_or_else(|| game_state.set(GameState::NothingSelected).unwrap());
+        .unwrap_or_else(|| change_state(&mut game_state, GameState::NothingSelected));
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -361,9 +1084,22 @@ pub fn apply_piece_move(
     mut game_state: ResMut<State<GameState>>,
     mut special_move_data: ResMut<SpecialMoveData>,
     mut promoted_pawn: ResMut<PromotedPawn>,
+    mut move_number: ResMut<MoveNumber>,
+    mut game_log: ResMut<GameLog>,
+    mut move_log: ResMut<MoveLog>,
+    mut piece_captured: EventWriter<PieceCaptured>,
+    board_scale: Res<BoardScale>,
     squares: Query<&Square>,
-    mut pieces: Query<(Entity, &mut Piece)>,
+    moves_in_progress: Query<(), With<MovePiece>>,
+    pieces: Query<(Entity, &Piece)>,
 ) {
+    // only one move can be committed per turn,
+    // so anything selected while the last move is still playing out is ignored
+    if !moves_in_progress.is_empty() {
+        change_state(&mut game_state, GameState::MovingPiece);
+        return;
+    }
+
     let square = if let Some(entity) = selected_square.0 {
         squares.get(entity).unwrap()
     } else {
@@ -372,15 +1108,56 @@ pub fn apply_piece_move(
 
     if let Some(piece_id) = selected_piece.0 {
         let valid_moves = all_valid_moves.get(piece_id);
-        let maybe_valid_move = valid_moves.iter().find(|m| m.target_square == *square);
+        // castling can be selected by clicking either the Rook or the King's destination
+        let maybe_valid_move = valid_moves.iter().find(|m| {
+            m.target_square == *square || m.castling_destination().contains(square)
+        });
         if let Some(valid_move) = maybe_valid_move {
-            let (_, piece) = pieces.get_mut(piece_id).unwrap();
+            // from here on, castling is always treated as the King moving onto the Rook
+            let square = &valid_move.target_square;
+
+            let last_pawn_double_step = special_move_data.last_pawn_double_step.as_ref();
+            if is_stale(piece_id, valid_move, player_turn.0, last_pawn_double_step, &pieces) {
+                warn!("Ignoring stale move to {:?}", valid_move.target_square);
+                change_state(&mut game_state, GameState::NothingSelected);
+                return;
+            }
+
+            let (_, piece) = pieces.get(piece_id).unwrap();
             let piece = *piece;
+            let board = pieces.iter().map(|(_, piece)| piece).collect::<BoardState>();
+            let captures = valid_move.is_capture(&board, piece.colour);
+            let all_pieces = pieces
+                .iter()
+                .map(|(entity, piece)| (entity, *piece))
+                .collect::<Vec<_>>();
+            let rivals = notation::rival_squares(&all_valid_moves, &all_pieces, piece_id, *square);
+            game_log.record_move(move_number.0, &piece, valid_move, captures, &rivals);
+            move_log.record_move(
+                notation::move_algebraic(&piece, valid_move, captures, &rivals),
+                notation::move_coordinates(&piece, valid_move),
+            );
             let _ = special_move_data.last_pawn_double_step.take();
+            special_move_data.last_move = Some(LastMove {
+                piece_id,
+                piece,
+                target_square: *square,
+            });
+            if player_turn.0 == PieceColour::Black {
+                move_number.0 += 1;
+            }
 
             if piece.kind == PieceKind::Pawn {
                 if let MoveKind::EnPassant { target_id } = valid_move.kind {
                     commands.entity(target_id).insert(Taken);
+                    if let Ok((_, taken)) = pieces.get(target_id) {
+                        piece_captured.send(PieceCaptured {
+                            by: piece.colour,
+                            kind: taken.kind,
+                            square: taken.square,
+                            en_passant: true,
+                        });
+                    }
                 } else if valid_move.kind == MoveKind::PawnDoubleStep {
                     let _ = special_move_data
                         .last_pawn_double_step
@@ -403,15 +1180,13 @@ pub fn apply_piece_move(
                     kingside,
                 } = valid_move.kind
                 {
-                    commands.entity(piece_id).insert(MovePiece::new(
-                        piece.square,
-                        (square.rank, king_target_y).into(),
-                    ));
-
-                    commands.entity(rook_id).insert(MovePiece::new(
-                        rook_position,
-                        (square.rank, rook_target_y).into(),
-                    ));
+                    let (king_move, rook_move) = MovePiece::castle(
+                        (piece.square, (square.rank, king_target_y).into()),
+                        (rook_position, (square.rank, rook_target_y).into()),
+                        *board_scale,
+                    );
+                    commands.entity(piece_id).insert(king_move);
+                    commands.entity(rook_id).insert(rook_move);
 
                     if kingside {
                         castling_data.kingside_rook_moved = true;
@@ -419,7 +1194,7 @@ pub fn apply_piece_move(
                         castling_data.queenside_rook_moved = true;
                     }
 
-                    game_state.set(GameState::MovingPiece).unwrap();
+                    change_state(&mut game_state, GameState::MovingPiece);
                     return;
                 }
             } else if piece.kind == PieceKind::Rook {
@@ -427,13 +1202,13 @@ pub fn apply_piece_move(
 
                 if piece.square.file == 0 {
                     castling_data.queenside_rook_moved = true;
-                } else if piece.square.file == 7 {
+                } else if piece.square.file == BOARD_SIZE - 1 {
                     castling_data.kingside_rook_moved = true;
                 }
             }
 
             if let Some((target_entity, target_piece)) = pieces
-                .iter_mut()
+                .iter()
                 .find(|(_, other)| other.square == *square) {
                 if target_piece.kind == PieceKind::Rook {
                     let other_player = player_turn.0.opposite();
@@ -444,56 +1219,137 @@ pub fn apply_piece_move(
                     {
                         castling_data.queenside_rook_moved = true;
                     } else if target_piece.square.rank == other_player.starting_back_rank()
-                        && target_piece.square.file == 7
+                        && target_piece.square.file == BOARD_SIZE - 1
                     {
                         castling_data.kingside_rook_moved = true;
                     }
                 };
 
                 commands.entity(target_entity).insert(Taken);
+                piece_captured.send(PieceCaptured {
+                    by: piece.colour,
+                    kind: target_piece.kind,
+                    square: target_piece.square,
+                    en_passant: false,
+                });
             }
 
             commands
                 .entity(piece_id)
-                .insert(MovePiece::new(piece.square, *square));
+                .insert(MovePiece::new(piece.square, *square, *board_scale));
 
-            game_state.set(GameState::MovingPiece).unwrap();
+            change_state(&mut game_state, GameState::MovingPiece);
         } else {
-            game_state.set(GameState::NothingSelected).unwrap();
+            change_state(&mut game_state, GameState::NothingSelected);
         };
     }
 }
 
+/// the valid moves are only calculated at the start of the turn, so if the board has changed since
+/// (e.g. the piece was taken or promoted), the selected move might not be playable any more
+fn is_stale(
+    piece_id: Entity,
+    valid_move: &Move,
+    turn: PieceColour,
+    last_pawn_double_step: Option<&LastPawnDoubleStep>,
+    pieces: &Query<(Entity, &Piece)>,
+) -> bool {
+    let Ok((_, piece)) = pieces.get(piece_id) else { return true };
+    if piece.colour != turn {
+        return true;
+    }
+
+    let blocked_by_own_piece = || {
+        pieces
+            .iter()
+            .any(|(_, other)| other.square == valid_move.target_square && other.colour == turn)
+    };
+
+    match valid_move.kind {
+        // en passant is only allowed straight after the double step, which has to be the one being taken
+        MoveKind::EnPassant { target_id } => {
+            blocked_by_own_piece()
+                || pieces.get(target_id).is_err()
+                || last_pawn_double_step.map(|double_step| double_step.pawn_id) != Some(target_id)
+        }
+        MoveKind::Castle {
+            rook_id,
+            rook_position,
+            ..
+        } => pieces
+            .get(rook_id)
+            .map_or(true, |(_, rook)| rook.square != rook_position),
+        MoveKind::Standard | MoveKind::PawnDoubleStep => blocked_by_own_piece(),
+    }
+}
+
 fn reset_selected(
+    mut commands: Commands,
     mut selected_square: ResMut<SelectedSquare>,
     mut selected_piece: ResMut<SelectedPiece>,
     mut valid_moves: ResMut<AllValidMoves>,
     mut highlighted: ResMut<Option<HighlightedSquare>>,
+    shown_moves: Query<Entity, Or<(With<ShowMoves>, With<ShowCastling>)>>,
 ) {
     selected_square.0 = None;
     selected_piece.0 = None;
     valid_moves.clear();
     *highlighted = None;
+    shown_moves.for_each(|entity| {
+        commands
+            .entity(entity)
+            .remove::<ShowMoves>()
+            .remove::<ShowCastling>();
+    });
 }
 
 fn despawn_taken_pieces(
     mut commands: Commands,
     mut state: ResMut<State<GameState>>,
     turn: Res<PlayerTurn>,
-    query: Query<(Entity, &Piece, &Taken)>,
+    animation_settings: Res<AnimationSettings>,
+    query: Query<(Entity, &Piece, &Transform), With<Taken>>,
 ) {
-    query.for_each(|(entity, piece, _)| {
+    query.for_each(|(entity, piece, transform)| {
         if piece.kind == PieceKind::King {
-            state.set(GameState::Checkmate(turn.0)).unwrap();
+            change_state(&mut state, GameState::Checkmate(turn.0));
         }
 
-        commands.entity(entity).despawn_recursive();
+        if animation_settings.instant {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            // the piece is off the board as far as the game is concerned, but stays visible until it's finished sinking
+            commands
+                .entity(entity)
+                .remove::<Piece>()
+                .insert(CaptureAnimation::new(transform.translation));
+        }
     })
 }
 
+fn sink_captured_pieces(
+    mut commands: Commands,
+    time: Res<Time>,
+    board_scale: Res<BoardScale>,
+    mut query: Query<(Entity, &mut CaptureAnimation, &mut Transform)>,
+) {
+    query.for_each_mut(|(entity, mut animation, mut transform)| {
+        animation.elapsed += time.delta_seconds();
+
+        if animation.elapsed >= CAPTURE_ANIMATION_SECONDS {
+            commands.entity(entity).despawn_recursive();
+            return;
+        }
+
+        let eased = ease_xz(animation.elapsed / CAPTURE_ANIMATION_SECONDS);
+        transform.translation = animation.start - (Vec3::Y * eased * board_scale.0);
+        transform.scale = mesh_scale(*board_scale) * (1.0 - eased);
+    });
+}
+
 fn restart_game(input: Res<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
     if input.just_pressed(KeyCode::R) {
-        state.set(GameState::NewGame).unwrap();
+        change_state(&mut state, GameState::NewGame);
     }
 }
 
@@ -501,65 +1357,140 @@ fn start_new_game(
     mut game_state: ResMut<State<GameState>>,
     mut turn: ResMut<PlayerTurn>,
     mut special_move_data: ResMut<SpecialMoveData>,
+    mut mating_move: ResMut<Option<MatingMove>>,
+    mut move_number: ResMut<MoveNumber>,
+    mut move_log: ResMut<MoveLog>,
 ) {
     turn.0 = PieceColour::White;
-    game_state.set(GameState::NothingSelected).unwrap();
+    change_state(&mut game_state, GameState::NothingSelected);
     *special_move_data = Default::default();
+    *mating_move = None;
+    *move_number = MoveNumber::default();
+    *move_log = MoveLog::default();
+}
+
+fn log_fen(
+    input: Res<Input<KeyCode>>,
+    turn: Res<PlayerTurn>,
+    move_number: Res<MoveNumber>,
+    special_move_data: Res<SpecialMoveData>,
+    pieces: Query<&Piece>,
+) {
+    if input.just_pressed(KeyCode::E) {
+        let pieces = pieces.iter().copied().collect::<Vec<_>>();
+        let fen = notation::to_fen(&pieces, turn.0, &special_move_data, move_number.0);
+        info!("Move {}, {} to move: {}", move_number.0, turn.0, fen);
+    }
+}
+
+/// logs the game so far as PGN, which can be pasted into most chess programs,
+/// and as coordinates, which can be saved to a file and replayed with `--moves`
+fn log_pgn(input: Res<Input<KeyCode>>, move_log: Res<MoveLog>, game_state: Res<State<GameState>>) {
+    if input.just_pressed(KeyCode::G) {
+        info!("\n{}", notation::to_pgn(&move_log.0, game_state.current().result()));
+        info!("Moves: {}", move_log.to_coordinate_string());
+    }
+}
+
+fn toggle_game_log(input: Res<Input<KeyCode>>, mut game_log: ResMut<GameLog>) {
+    if input.just_pressed(KeyCode::L) {
+        game_log.verbosity = match game_log.verbosity {
+            GameLogVerbosity::Off => GameLogVerbosity::Moves,
+            GameLogVerbosity::Moves => GameLogVerbosity::MovesAndPositions,
+            GameLogVerbosity::MovesAndPositions => GameLogVerbosity::Off,
+        };
+        info!("Game log verbosity: {:?}", game_log.verbosity);
+    }
+}
+
+pub fn log_game(
+    mut game_log: ResMut<GameLog>,
+    game_state: Res<State<GameState>>,
+    turn: Res<PlayerTurn>,
+    move_number: Res<MoveNumber>,
+    special_move_data: Res<SpecialMoveData>,
+    pieces: Query<&Piece>,
+) {
+    if game_log.verbosity == GameLogVerbosity::Off {
+        return;
+    }
+
+    let state = game_state.current();
+    // the move isn't finished until the pieces have arrived and any promotion has been chosen
+    if *state == GameState::NothingSelected || state.is_game_over() {
+        if let Some(mut line) = game_log.pending_move.take() {
+            if game_log.verbosity == GameLogVerbosity::MovesAndPositions {
+                let pieces = pieces.iter().copied().collect::<Vec<_>>();
+                let fen = notation::to_fen(&pieces, turn.0, &special_move_data, move_number.0);
+                line = format!("{} {}", line, fen);
+            }
+            game_log.log(line);
+        }
+    }
+
+    let Some(result) = state.result() else {
+        game_log.result_logged = false;
+        return;
+    };
+
+    if !game_log.result_logged {
+        game_log.result_logged = true;
+        game_log.log(format!("{} {:?}", result, state));
+    }
 }
 
 fn translate_moved_pieces(
     mut commands: Commands,
     time: Res<Time>,
+    move_easing: Res<MoveEasing>,
     promoted_pawn: Res<PromotedPawn>,
     mut state: ResMut<State<GameState>>,
     mut turn: ResMut<PlayerTurn>,
+    mut pawn_promotable: EventWriter<PawnPromotable>,
     mut query: Query<(Entity, &mut MovePiece, &mut Piece, &mut Transform)>,
 ) {
-    // note: castling moves two pieces on the same turn
-
-    let average_velocity = 5.0;
-
-    let any_updated =
-        query
-            .iter_mut()
-            .any(|(piece_entity, mut move_piece, mut piece, mut transform)| {
-                let direction = move_piece.to - transform.translation;
-
-                if direction.length() > f32::EPSILON {
-                    let distance = (move_piece.from - move_piece.to).length();
-                    let target_time = distance.sqrt() / average_velocity;
-
-                    move_piece.elapsed += time.delta_seconds();
-                    if move_piece.elapsed > target_time {
-                        transform.translation = move_piece.to;
-                    } else {
-                        let t = move_piece.elapsed / target_time;
-                        let eased = ease_xz(t);
+    // note: castling moves two pieces on the same turn, so every piece has to be updated,
+    // and the turn only ends once all of them have arrived
+    let mut any_updated = false;
 
-                        let xz_translation = move_piece.from.lerp(move_piece.to, eased);
+    query.for_each_mut(|(piece_entity, mut move_piece, mut piece, mut transform)| {
+        let direction = move_piece.to - transform.translation;
 
-                        let max_height = 0.5 * distance.sqrt();
-                        let y_translation = Vec3::new(0.0, ease_y(t) * max_height, 0.0);
+        if direction.length() > f32::EPSILON {
+            move_piece.elapsed += time.delta_seconds();
+            if move_piece.elapsed > move_piece.duration {
+                transform.translation = move_piece.to;
+            } else {
+                let t = move_piece.elapsed / move_piece.duration;
+                let eased = move_easing.apply(t);
 
-                        transform.translation = xz_translation + y_translation;
-                    }
+                let xz_translation = move_piece.from.lerp(move_piece.to, eased);
+                let y_translation = Vec3::new(0.0, ease_y(t) * move_piece.max_height, 0.0);
 
-                    true
-                } else {
-                    piece.square = move_piece.target_square();
+                transform.translation = xz_translation + y_translation;
+            }
 
-                    commands.entity(piece_entity).remove::<MovePiece>();
+            any_updated = true;
+        } else {
+            piece.square = move_piece.target_square();
 
-                    false
-                }
-            });
+            commands.entity(piece_entity).remove::<MovePiece>();
+        }
+    });
 
     if !any_updated {
-        if promoted_pawn.0.is_some() {
-            state.set(GameState::PawnPromotion).unwrap();
+        if let Some(pawn) = promoted_pawn.0 {
+            if let Ok((_, _, piece, _)) = query.get(pawn) {
+                pawn_promotable.send(PawnPromotable {
+                    entity: pawn,
+                    square: piece.square,
+                    colour: piece.colour,
+                });
+            }
+            change_state(&mut state, GameState::PawnPromotion);
         } else {
             turn.next();
-            state.set(GameState::NothingSelected).unwrap();
+            change_state(&mut state, GameState::NothingSelected);
         }
     }
 }
@@ -581,60 +1512,134 @@ fn promote_pawn_at_final_rank(
     mut game_state: ResMut<State<GameState>>,
     mut turn: ResMut<PlayerTurn>,
     mut promoted_pawn: ResMut<PromotedPawn>,
+    mut move_log: ResMut<MoveLog>,
+    mut scripted_moves: ResMut<ScriptedMoves>,
     input: Res<Input<KeyCode>>,
+    animation_settings: Res<AnimationSettings>,
+    promotion_settings: Res<PromotionSettings>,
+    board_scale: Res<BoardScale>,
     meshes: Res<PieceMeshes>,
     materials: Res<PieceMaterials>,
     pieces: Query<(Entity, &Piece)>,
 ) {
-    let entity = promoted_pawn
-        .0
-        .expect("should always have a promoted pawn entity when in PawnPromotion state");
-    let (_, piece) = pieces
-        .get(entity)
-        .expect("promoted pawn should always exist");
+    let Some((entity, piece)) = promoted_pawn.0.and_then(|entity| pieces.get(entity).ok()) else {
+        // there's nothing to choose a promotion for, so the best that can be done is to carry on
+        warn!("No promoted pawn found during pawn promotion; ending the turn");
+        promoted_pawn.0 = None;
+        turn.next();
+        change_state(&mut game_state, GameState::NothingSelected);
+        return;
+    };
 
-    if input.just_pressed(KeyCode::Return) && piece.kind != PieceKind::Pawn {
+    if input.just_pressed(KeyCode::Return) && PROMOTIONS.contains(&piece.kind) {
+        move_log.record_promotion(piece.kind);
         promoted_pawn.0 = None;
         turn.next();
-        game_state.set(GameState::NothingSelected).unwrap();
+        change_state(&mut game_state, GameState::NothingSelected);
     };
 
-    let promotions = [
-        PieceKind::Knight,
-        PieceKind::Bishop,
-        PieceKind::Rook,
-        PieceKind::Queen,
-    ];
+    // replaced straight away, so this is only warned about once rather than every frame
+    let unexpected_kind = piece.kind != PieceKind::Pawn && !PROMOTIONS.contains(&piece.kind);
+    if unexpected_kind {
+        warn!(
+            "{:?} on {} shouldn't be getting promoted; replacing it with a Queen",
+            piece.kind, piece.square
+        );
+    }
 
-    let index_of = |kind: PieceKind| {
-        promotions
-            .iter()
-            .enumerate()
-            .find_map(|(idx, k)| (*k == kind).then(|| idx))
-            .unwrap_or_else(|| panic!(
-                "promoted to unexpected piece kind {:?}",
-                piece.kind
-            ))
+    let auto_queen = piece.kind == PieceKind::Pawn
+        && promotion_settings.mode(piece.colour) == PromotionMode::AlwaysQueen;
+    let scripted_promotion = if piece.kind == PieceKind::Pawn {
+        scripted_moves.take_promotion()
+    } else {
+        None
     };
 
-    let new_kind = if piece.kind == PieceKind::Pawn && input.just_pressed(KeyCode::Left) {
+    let new_kind = if let Some(kind) = scripted_promotion {
+        kind
+    } else if auto_queen || unexpected_kind {
         PieceKind::Queen
-    } else if piece.kind == PieceKind::Pawn && input.just_pressed(KeyCode::Right) {
-        PieceKind::Knight
     } else if input.just_pressed(KeyCode::Left) {
-        let index = index_of(piece.kind);
-        promotions[(index as isize - 1) as usize % promotions.len()]
+        previous_promotion(piece.kind)
     } else if input.just_pressed(KeyCode::Right) {
-        let index = index_of(piece.kind);
-        promotions[(index + 1) % promotions.len()]
+        next_promotion(piece.kind)
     } else {
         return;
     };
 
-    let square = piece.square;
+    // the promoted piece takes the pawn's colour, rather than trusting that the turn hasn't moved on yet
+    let Piece { square, colour, .. } = *piece;
     commands.entity(entity).despawn_recursive();
 
-    let new_entity =
-        game_set_up::spawn_piece(&mut commands, &materials, &meshes, turn.0, new_kind, square);
-    promoted_pawn.0 = Some(new_entity);
+    let new_entity = game_set_up::spawn_piece(
+        &mut commands,
+        &materials,
+        &meshes,
+        *board_scale,
+        colour,
+        new_kind,
+        square,
+    );
+
+    if !animation_settings.instant {
+        let mut transform = place_on_square(*board_scale, colour, square);
+        transform.scale = Vec3::ZERO;
+
+        commands
+            .entity(new_entity)
+            .insert(transform)
+            .insert(PromotionAnimation::default());
+    }
+
+    if auto_queen || scripted_promotion.is_some() {
+        move_log.record_promotion(new_kind);
+        promoted_pawn.0 = None;
+        turn.next();
+        change_state(&mut game_state, GameState::NothingSelected);
+    } else {
+        promoted_pawn.0 = Some(new_entity);
+    }
+}
+
+const PROMOTIONS: [PieceKind; 4] = [
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+];
+
+/// cycles backwards through the promotion options, starting from a Queen if nothing has been chosen yet
+fn previous_promotion(current: PieceKind) -> PieceKind {
+    match PROMOTIONS.iter().position(|kind| *kind == current) {
+        Some(index) => PROMOTIONS[(index + PROMOTIONS.len() - 1) % PROMOTIONS.len()],
+        None => PieceKind::Queen,
+    }
+}
+
+/// cycles forwards through the promotion options, starting from a Knight if nothing has been chosen yet
+fn next_promotion(current: PieceKind) -> PieceKind {
+    match PROMOTIONS.iter().position(|kind| *kind == current) {
+        Some(index) => PROMOTIONS[(index + 1) % PROMOTIONS.len()],
+        None => PieceKind::Knight,
+    }
+}
+
+fn grow_promoted_pieces(
+    mut commands: Commands,
+    time: Res<Time>,
+    board_scale: Res<BoardScale>,
+    mut query: Query<(Entity, &mut PromotionAnimation, &mut Transform)>,
+) {
+    query.for_each_mut(|(entity, mut animation, mut transform)| {
+        animation.elapsed += time.delta_seconds();
+
+        let scale = if animation.elapsed >= PROMOTION_ANIMATION_SECONDS {
+            commands.entity(entity).remove::<PromotionAnimation>();
+            1.0
+        } else {
+            ease_xz(animation.elapsed / PROMOTION_ANIMATION_SECONDS)
+        };
+
+        transform.scale = mesh_scale(*board_scale) * scale;
+    });
 }
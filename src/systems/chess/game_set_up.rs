@@ -1,4 +1,4 @@
-use crate::model::{Piece, PieceColour, PieceKind, Square};
+use crate::model::{BoardScale, Piece, PieceColour, PieceKind, Square, BOARD_SIZE};
 use super::GameState;
 use bevy::prelude::*;
 use std::f32::consts::PI;
@@ -7,10 +7,12 @@ use bevy_mod_picking::PickableBundle;
 pub struct GameSetUpPlugin;
 impl Plugin for GameSetUpPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<SquareMaterials>()
+        app.init_resource::<Theme>()
+            .init_resource::<SquareMaterials>()
             .init_resource::<PieceMeshes>()
             .init_resource::<PieceMaterials>()
             .add_startup_system(create_board)
+            .add_startup_system(create_border)
             .add_startup_system(create_floor_plane)
             .add_startup_system(create_pieces)
             .add_system_set(
@@ -23,24 +25,31 @@ fn reset_pieces(
     mut commands: Commands,
     meshes: Res<PieceMeshes>,
     materials: Res<PieceMaterials>,
+    board_scale: Res<BoardScale>,
     pieces: Query<Entity, With<Piece>>,
 ) {
     pieces.for_each(|entity| commands.entity(entity).despawn_recursive());
-    create_pieces(commands, meshes, materials);
+    create_pieces(commands, meshes, materials, board_scale);
 }
 
+/// the meshes are modelled much smaller than a single square
 const SCALE_FACTOR: f32 = 15.0;
 
+pub(super) fn mesh_scale(board_scale: BoardScale) -> Vec3 {
+    Vec3::splat(SCALE_FACTOR * board_scale.0)
+}
+
 fn create_board(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     assets: Res<AssetServer>,
     materials: ResMut<SquareMaterials>,
+    board_scale: Res<BoardScale>,
 ) {
     let chessboard = assets.load("meshes/chessboard.glb#Scene0");
 
-    let scale = Transform::from_scale(Vec3::splat(SCALE_FACTOR));
-    let translation = Transform::from_xyz(0.0, -0.062 * SCALE_FACTOR, 0.0);
+    let scale = Transform::from_scale(mesh_scale(*board_scale));
+    let translation = Transform::from_xyz(0.0, -0.062 * SCALE_FACTOR * board_scale.0, 0.0);
     let transform = translation * scale;
 
     commands
@@ -51,23 +60,92 @@ fn create_board(
 
     let mesh = meshes.add(Mesh::from(shape::Plane { size: 1.0 }));
 
-    (0..8).for_each(|rank| {
-        (0..8).for_each(|file| {
-            let square = Square { rank, file };
-
-            commands
-                .spawn_bundle(PbrBundle {
-                    mesh: mesh.clone(),
-                    material: materials.none.clone(),
-                    transform: Transform::from_translation(square.to_translation()),
+    Square::all().for_each(|square| {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: mesh.clone(),
+                material: materials.none.clone(),
+                transform: Transform {
+                    translation: square.to_translation(*board_scale),
+                    scale: Vec3::splat(board_scale.0),
                     ..Default::default()
-                })
-                .insert_bundle(PickableBundle::default())
-                .insert(square);
-        })
+                },
+                ..Default::default()
+            })
+            .insert_bundle(PickableBundle::default())
+            .insert(square);
     })
 }
 
+/// colours used to decorate the board, as opposed to the ones that show the state of the game
+pub struct Theme {
+    pub border: Color,
+    /// how the selected piece glows; this is baked into `PieceMaterials` when they're created
+    pub selection_glow: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            border: Color::rgb(0.25, 0.15, 0.08),
+            selection_glow: Color::rgb(0.6, 0.4, 0.0),
+        }
+    }
+}
+
+/// how wide the frame around the board is, in squares
+const BORDER_WIDTH: f32 = 0.5;
+const BORDER_HEIGHT: f32 = 0.1;
+
+/// the centre and size of each of the four sides of the frame, which surrounds the squares without
+/// overlapping them
+pub fn border_segments(board_scale: BoardScale) -> [(Vec3, Vec3); 4] {
+    let half_board = BOARD_SIZE as f32 / 2.0;
+    let offset = half_board + BORDER_WIDTH / 2.0;
+    // the top of the frame is level with the squares
+    let y = -BORDER_HEIGHT / 2.0;
+
+    let across = Vec3::new(
+        BOARD_SIZE as f32 + 2.0 * BORDER_WIDTH,
+        BORDER_HEIGHT,
+        BORDER_WIDTH,
+    );
+    let along = Vec3::new(BORDER_WIDTH, BORDER_HEIGHT, BOARD_SIZE as f32);
+
+    [
+        (Vec3::new(0.0, y, -offset), across),
+        (Vec3::new(0.0, y, offset), across),
+        (Vec3::new(-offset, y, 0.0), along),
+        (Vec3::new(offset, y, 0.0), along),
+    ]
+    .map(|(centre, size)| (centre * board_scale.0, size * board_scale.0))
+}
+
+fn create_border(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    theme: Res<Theme>,
+    board_scale: Res<BoardScale>,
+) {
+    let material = materials.add(StandardMaterial {
+        base_color: theme.border,
+        perceptual_roughness: 0.8,
+        ..Default::default()
+    });
+
+    border_segments(*board_scale)
+        .into_iter()
+        .for_each(|(centre, size)| {
+            commands.spawn_bundle(PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Box::new(size.x, size.y, size.z))),
+                material: material.clone(),
+                transform: Transform::from_translation(centre),
+                ..Default::default()
+            });
+        });
+}
+
 fn create_floor_plane(mut commands: Commands, assets: Res<AssetServer>) {
     // doesn't appear to support instancing
     let plane = assets.load("meshes/floor.glb#Scene0");
@@ -87,59 +165,39 @@ fn create_floor_plane(mut commands: Commands, assets: Res<AssetServer>) {
     }
 }
 
-fn create_pieces(mut commands: Commands, meshes: Res<PieceMeshes>, materials: Res<PieceMaterials>) {
-    [PieceColour::White, PieceColour::Black]
-        .into_iter()
-        .for_each(|colour| {
-            let back_row = colour.starting_back_rank();
-            let front_row = colour.starting_front_rank();
-
-            [
-                PieceKind::Rook,
-                PieceKind::Knight,
-                PieceKind::Bishop,
-                PieceKind::Queen,
-                PieceKind::King,
-                PieceKind::Bishop,
-                PieceKind::Knight,
-                PieceKind::Rook,
-            ]
-                .into_iter()
-                .enumerate()
-                .for_each(|(file, kind)| {
-                    spawn_piece(
-                        &mut commands,
-                        &materials,
-                        &meshes,
-                        colour,
-                        kind,
-                        (back_row, file as u8).into(),
-                    );
-                });
-
-            (0..=7).for_each(|file| {
-                spawn_piece(
-                    &mut commands,
-                    &materials,
-                    &meshes,
-                    colour,
-                    PieceKind::Pawn,
-                    (front_row, file).into(),
-                );
-            });
-        });
+fn create_pieces(
+    mut commands: Commands,
+    meshes: Res<PieceMeshes>,
+    materials: Res<PieceMaterials>,
+    board_scale: Res<BoardScale>,
+) {
+    Piece::starting_position().into_iter().for_each(|piece| {
+        spawn_piece(
+            &mut commands,
+            &materials,
+            &meshes,
+            *board_scale,
+            piece.colour,
+            piece.kind,
+            piece.square,
+        );
+    });
 }
 
 pub fn spawn_piece(
     commands: &mut Commands,
     materials: &PieceMaterials,
     meshes: &PieceMeshes,
+    board_scale: BoardScale,
     colour: PieceColour,
     kind: PieceKind,
     square: Square,
 ) -> Entity {
     commands
-        .spawn_bundle((place_on_square(colour, square), GlobalTransform::identity()))
+        .spawn_bundle((
+            place_on_square(board_scale, colour, square),
+            GlobalTransform::identity(),
+        ))
         .insert(Piece {
             colour,
             kind,
@@ -155,17 +213,21 @@ pub fn spawn_piece(
         .id()
 }
 
-fn place_on_square(colour: PieceColour, square: Square) -> Transform {
+pub(super) fn place_on_square(
+    board_scale: BoardScale,
+    colour: PieceColour,
+    square: Square,
+) -> Transform {
     let angle = if colour == PieceColour::Black {
         PI
     } else {
         0.0
     };
 
-    let scale = Transform::from_scale(Vec3::splat(SCALE_FACTOR));
+    let scale = Transform::from_scale(mesh_scale(board_scale));
     let rotation = Transform::from_rotation(Quat::from_rotation_y(angle));
 
-    let translation = Transform::from_translation(square.to_translation());
+    let translation = Transform::from_translation(square.to_translation(board_scale));
 
     translation * rotation * scale
 }
@@ -174,9 +236,30 @@ pub struct SquareMaterials {
     pub highlight: Handle<StandardMaterial>,
     pub selected: Handle<StandardMaterial>,
     pub valid_selection: Handle<StandardMaterial>,
+    /// the castling Rook and the King's destination, which are easy to miss as normal moves
+    pub castling: Handle<StandardMaterial>,
+    /// includes en passant, even though the target square is empty
+    pub capture: Handle<StandardMaterial>,
     pub none: Handle<StandardMaterial>,
 }
 
+impl SquareMaterials {
+    /// every material is a different (empty) handle, so tests can tell which one a square was given
+    #[cfg(test)]
+    pub fn placeholder() -> Self {
+        let handle = || Handle::weak(bevy::asset::HandleId::random::<StandardMaterial>());
+
+        Self {
+            highlight: handle(),
+            selected: handle(),
+            valid_selection: handle(),
+            castling: handle(),
+            capture: handle(),
+            none: handle(),
+        }
+    }
+}
+
 impl FromWorld for SquareMaterials {
     fn from_world(world: &mut World) -> Self {
         let assets = world.get_resource::<AssetServer>().unwrap();
@@ -199,6 +282,18 @@ impl FromWorld for SquareMaterials {
                 ..Default::default()
             }),
             valid_selection: materials.add(StandardMaterial {
+                base_color_texture: Some(valid_selection.clone()),
+                alpha_mode: AlphaMode::Blend,
+                ..Default::default()
+            }),
+            castling: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.4, 0.6, 1.0),
+                base_color_texture: Some(valid_selection.clone()),
+                alpha_mode: AlphaMode::Blend,
+                ..Default::default()
+            }),
+            capture: materials.add(StandardMaterial {
+                base_color: Color::rgb(1.0, 0.4, 0.4),
                 base_color_texture: Some(valid_selection),
                 alpha_mode: AlphaMode::Blend,
                 ..Default::default()
@@ -222,6 +317,18 @@ pub struct PieceMeshes {
 }
 
 impl PieceMeshes {
+    #[cfg(test)]
+    pub fn placeholder() -> Self {
+        Self {
+            king: Handle::default(),
+            pawn: Handle::default(),
+            knight: Handle::default(),
+            rook: Handle::default(),
+            bishop: Handle::default(),
+            queen: Handle::default(),
+        }
+    }
+
     pub fn get(&self, kind: PieceKind) -> Handle<Mesh> {
         match kind {
             PieceKind::King => self.king.clone(),
@@ -254,6 +361,10 @@ impl FromWorld for PieceMeshes {
 pub struct PieceMaterials {
     pub white: Handle<StandardMaterial>,
     pub black: Handle<StandardMaterial>,
+    pub white_preview: Handle<StandardMaterial>,
+    pub black_preview: Handle<StandardMaterial>,
+    pub white_selected: Handle<StandardMaterial>,
+    pub black_selected: Handle<StandardMaterial>,
 }
 
 impl PieceMaterials {
@@ -263,16 +374,77 @@ impl PieceMaterials {
             PieceColour::Black => self.black.clone(),
         }
     }
+
+    /// a translucent version of the piece's material, for showing where it could move to
+    pub fn preview(&self, piece_colour: PieceColour) -> Handle<StandardMaterial> {
+        match piece_colour {
+            PieceColour::White => self.white_preview.clone(),
+            PieceColour::Black => self.black_preview.clone(),
+        }
+    }
+
+    /// a glowing version of the piece's material, for showing it's been selected
+    pub fn selected(&self, piece_colour: PieceColour) -> Handle<StandardMaterial> {
+        match piece_colour {
+            PieceColour::White => self.white_selected.clone(),
+            PieceColour::Black => self.black_selected.clone(),
+        }
+    }
+
+    /// every material is a different (empty) handle, so tests can tell which one a piece was given
+    #[cfg(test)]
+    pub fn placeholder() -> Self {
+        let handle = || Handle::weak(bevy::asset::HandleId::random::<StandardMaterial>());
+
+        Self {
+            white: handle(),
+            black: handle(),
+            white_preview: handle(),
+            black_preview: handle(),
+            white_selected: handle(),
+            black_selected: handle(),
+        }
+    }
 }
 
 impl FromWorld for PieceMaterials {
     fn from_world(world: &mut World) -> Self {
+        let glow = world
+            .get_resource_or_insert_with(Theme::default)
+            .selection_glow;
         let mut materials = world
             .get_resource_mut::<Assets<StandardMaterial>>()
             .unwrap();
         let black = materials.add(Color::rgb(0.0, 0.2, 0.2).into());
         let white = materials.add(Color::rgb(1.0, 0.8, 0.8).into());
 
-        Self { white, black }
+        let mut preview = |color: Color| {
+            materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                ..Default::default()
+            })
+        };
+        let black_preview = preview(Color::rgba(0.0, 0.2, 0.2, 0.4));
+        let white_preview = preview(Color::rgba(1.0, 0.8, 0.8, 0.4));
+
+        let mut selected = |color: Color| {
+            materials.add(StandardMaterial {
+                base_color: color,
+                emissive: glow,
+                ..Default::default()
+            })
+        };
+        let black_selected = selected(Color::rgb(0.0, 0.2, 0.2));
+        let white_selected = selected(Color::rgb(1.0, 0.8, 0.8));
+
+        Self {
+            white,
+            black,
+            white_preview,
+            black_preview,
+            white_selected,
+            black_selected,
+        }
     }
 }
@@ -0,0 +1,201 @@
+use super::scripted_moves::{play_scripted_move, ScriptError, ScriptedMoves};
+use super::{
+    apply_piece_move, calculate_all_moves, change_state, mark_check_in_move_log, GameLog,
+    GameState, MatingMove, MoveLog, MoveNumber, MovePiece, PerformanceStats, PieceCaptured,
+    PlayerTurn, PromotedPawn, SelectedPiece, SelectedSquare, Taken,
+};
+use crate::model::{
+    AllValidMoves, BoardScale, CheckState, Piece, PieceColour, PieceKind, SpecialMoveData, Square,
+};
+use crate::random::{random_move, SeededRng};
+use bevy::app::Events;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// plays a game with the same systems as `ChessPlugin`, but without rendering or animating anything,
+/// so whole turns can be measured rather than just the moves calculator
+///
+/// promotions are to a Queen, since there's nobody to choose, unless a scripted move says otherwise
+pub struct HeadlessGame {
+    world: World,
+    stage: SystemStage,
+    squares: HashMap<Square, Entity>,
+}
+
+impl HeadlessGame {
+    pub fn new() -> Self {
+        Self::from_position(Piece::starting_position(), PieceColour::White)
+    }
+
+    pub fn from_position(pieces: Vec<Piece>, turn: PieceColour) -> Self {
+        let mut world = World::new();
+
+        world.insert_resource(AllValidMoves::default());
+        world.insert_resource(CheckState::default());
+        world.insert_resource::<Option<MatingMove>>(None);
+        world.insert_resource(PerformanceStats::default());
+        world.insert_resource(PlayerTurn(turn));
+        world.insert_resource(State::new(GameState::NothingSelected));
+        world.insert_resource(SpecialMoveData::from_position(&pieces));
+        world.insert_resource(SelectedSquare::default());
+        world.insert_resource(SelectedPiece::default());
+        world.insert_resource(PromotedPawn::default());
+        world.insert_resource(MoveNumber::default());
+        world.insert_resource(GameLog::default());
+        world.insert_resource(MoveLog::default());
+        world.insert_resource(ScriptedMoves::default());
+        world.insert_resource(Events::<PieceCaptured>::default());
+        world.insert_resource(BoardScale::default());
+
+        let squares = Square::all()
+            .map(|square| (square, world.spawn().insert(square).id()))
+            .collect();
+        pieces.into_iter().for_each(|piece| {
+            world.spawn().insert(piece);
+        });
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system_set(State::<GameState>::get_driver());
+        stage.add_system(Events::<PieceCaptured>::update_system);
+        stage.add_system_set(
+            SystemSet::on_update(GameState::NothingSelected)
+                .with_system(calculate_all_moves.label("calculate_moves"))
+                .with_system(mark_check_in_move_log.after("calculate_moves"))
+                .with_system(play_scripted_move.after("calculate_moves")),
+        );
+        stage.add_system_set(
+            SystemSet::on_update(GameState::TargetSquareSelected).with_system(apply_piece_move),
+        );
+        stage.add_system_set(SystemSet::on_update(GameState::MovingPiece).with_system(finish_move));
+
+        let mut game = Self {
+            world,
+            stage,
+            squares,
+        };
+        game.stage.run(&mut game.world);
+        game
+    }
+
+    /// plays a game back from the start from its moves in coordinate notation, e.g. from
+    /// `MoveLog::to_coordinate_string`, stopping at the first one that isn't legal
+    pub fn from_coordinate_string(moves: &str) -> Result<Self, ScriptError> {
+        let mut game = Self::new();
+        game.play_scripted_moves(ScriptedMoves::new(moves))?;
+        Ok(game)
+    }
+
+    pub fn state(&self) -> &GameState {
+        self.world
+            .get_resource::<State<GameState>>()
+            .unwrap()
+            .current()
+    }
+
+    pub fn move_log(&self) -> &MoveLog {
+        self.world.get_resource::<MoveLog>().unwrap()
+    }
+
+    pub fn pieces(&mut self) -> Vec<Piece> {
+        self.world
+            .query::<&Piece>()
+            .iter(&self.world)
+            .copied()
+            .collect()
+    }
+
+    /// plays up to `plies` moves, chosen at random from the legal moves; the same seed always plays
+    /// the same moves from the same position
+    ///
+    /// stops early if the game ends, and returns how many moves were actually played
+    pub fn play_random_moves(&mut self, seed: u64, plies: usize) -> usize {
+        let mut rng = SeededRng::new(seed);
+
+        for ply in 0..plies {
+            if self.state().is_game_over() {
+                return ply;
+            }
+
+            let turn = self.world.get_resource::<PlayerTurn>().unwrap().0;
+            let all_valid_moves = self.world.get_resource::<AllValidMoves>().unwrap();
+            let Some((piece_id, target)) = random_move(all_valid_moves, &mut rng) else { warn!("{} has no moves, but the game isn't over", turn); return ply };
+
+            self.world.get_resource_mut::<SelectedPiece>().unwrap().0 = Some(piece_id);
+            self.world.get_resource_mut::<SelectedSquare>().unwrap().0 =
+                self.squares.get(&target).copied();
+            let _ = self
+                .world
+                .get_resource_mut::<State<GameState>>()
+                .unwrap()
+                .overwrite_set(GameState::TargetSquareSelected);
+
+            // once to play the move, and once more to calculate the reply
+            self.stage.run(&mut self.world);
+            self.stage.run(&mut self.world);
+        }
+
+        plies
+    }
+
+    /// plays every move in order, e.g. from a bug report, stopping at the first one that isn't legal
+    ///
+    /// returns how many moves were played
+    pub fn play_scripted_moves(&mut self, moves: ScriptedMoves) -> Result<usize, ScriptError> {
+        self.world.insert_resource(moves);
+
+        loop {
+            let game_over = self.state().is_game_over();
+            let mut moves = self.world.get_resource_mut::<ScriptedMoves>().unwrap();
+            if game_over && !moves.is_finished() {
+                moves.abort("the game is already over");
+            }
+
+            if let Some(error) = moves.error() {
+                return Err(error.clone());
+            }
+            if moves.is_finished() {
+                return Ok(moves.played());
+            }
+
+            self.stage.run(&mut self.world);
+        }
+    }
+}
+
+impl Default for HeadlessGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// stands in for `translate_moved_pieces`, `despawn_taken_pieces`, and `promote_pawn_at_final_rank`
+/// all at once, since nothing needs to be animated
+#[allow(clippy::too_many_arguments)]
+fn finish_move(
+    mut commands: Commands,
+    mut state: ResMut<State<GameState>>,
+    mut turn: ResMut<PlayerTurn>,
+    mut promoted_pawn: ResMut<PromotedPawn>,
+    mut move_log: ResMut<MoveLog>,
+    mut scripted_moves: ResMut<ScriptedMoves>,
+    mut moved: Query<(Entity, &MovePiece, &mut Piece)>,
+    taken: Query<Entity, With<Taken>>,
+) {
+    taken.for_each(|entity| commands.entity(entity).despawn());
+
+    moved.for_each_mut(|(entity, move_piece, mut piece)| {
+        piece.square = move_piece.target_square();
+        commands.entity(entity).remove::<MovePiece>();
+    });
+
+    if let Some(pawn) = promoted_pawn.0.take() {
+        if let Ok((_, _, mut piece)) = moved.get_mut(pawn) {
+            let kind = scripted_moves.take_promotion().unwrap_or(PieceKind::Queen);
+            piece.kind = kind;
+            move_log.record_promotion(kind);
+        }
+    }
+
+    turn.next();
+    change_state(&mut state, GameState::NothingSelected);
+}
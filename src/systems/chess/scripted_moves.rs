@@ -0,0 +1,158 @@
+use super::{change_state, GameState, PlayerTurn, SelectedPiece, SelectedSquare};
+use crate::model::{AllValidMoves, Piece, PieceColour, PieceKind, Square};
+use crate::notation;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::fmt::Formatter;
+use std::path::Path;
+
+/// moves to play at startup as if they'd been clicked on, e.g. to reproduce a bug report
+///
+/// the moves are in coordinate notation, e.g. "e2e4", separated by spaces or newlines.
+/// a promotion can be given as a suffix, e.g. "e7e8n"; without one, a pawn reaching the final rank
+/// is promoted however promotions are normally chosen
+#[derive(Debug, Default)]
+pub struct ScriptedMoves {
+    moves: VecDeque<String>,
+    played: usize,
+    error: Option<ScriptError>,
+    promotion: Option<PieceKind>,
+}
+
+impl ScriptedMoves {
+    pub fn new(moves: &str) -> Self {
+        Self {
+            moves: moves.split_whitespace().map(String::from).collect(),
+            played: 0,
+            error: None,
+            promotion: None,
+        }
+    }
+
+    /// as if a scripted move promoting to `kind` has just been played
+    #[cfg(test)]
+    pub fn promoting_to(kind: PieceKind) -> Self {
+        Self {
+            promotion: Some(kind),
+            ..Default::default()
+        }
+    }
+
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        std::fs::read_to_string(path).map(|moves| Self::new(&moves))
+    }
+
+    pub fn played(&self) -> usize {
+        self.played
+    }
+
+    /// the reason the moves stopped being played, if they didn't all get played
+    pub fn error(&self) -> Option<&ScriptError> {
+        self.error.as_ref()
+    }
+
+    /// the piece the move just played promotes to, if it gave one; it can only be taken once
+    pub fn take_promotion(&mut self) -> Option<PieceKind> {
+        self.promotion.take()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.moves.is_empty() || self.error.is_some()
+    }
+
+    /// gives up on the rest of the moves, blaming the next one
+    pub fn abort(&mut self, reason: impl Into<String>) {
+        if let Some(move_) = self.moves.pop_front() {
+            self.error = Some(ScriptError {
+                move_number: self.played + 1,
+                move_,
+                reason: reason.into(),
+            });
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptError {
+    /// counts each player's move separately, starting from 1
+    pub move_number: usize,
+    pub move_: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Couldn't play move {} ({}): {}",
+            self.move_number, self.move_, self.reason
+        )
+    }
+}
+
+/// selects the piece and square for the next scripted move, the same as `select_piece` and
+/// `select_square` would for clicks, and stops at the first move that isn't legal
+#[allow(clippy::too_many_arguments)]
+pub fn play_scripted_move(
+    mut scripted_moves: ResMut<ScriptedMoves>,
+    mut selected_piece: ResMut<SelectedPiece>,
+    mut selected_square: ResMut<SelectedSquare>,
+    mut game_state: ResMut<State<GameState>>,
+    turn: Res<PlayerTurn>,
+    all_valid_moves: Res<AllValidMoves>,
+    pieces: Query<(Entity, &Piece)>,
+    squares: Query<(Entity, &Square)>,
+) {
+    if scripted_moves.is_finished() {
+        return;
+    }
+
+    let pieces = pieces
+        .iter()
+        .map(|(entity, piece)| (entity, *piece))
+        .collect::<Vec<_>>();
+    let next_move = &scripted_moves.moves[0];
+    let (piece_id, target, promotion) =
+        match find_move(next_move, turn.0, &all_valid_moves, &pieces) {
+            Ok(found) => found,
+            Err(reason) => {
+                scripted_moves.abort(reason);
+                return;
+            }
+        };
+
+    scripted_moves.moves.pop_front();
+    scripted_moves.played += 1;
+    scripted_moves.promotion = promotion;
+    selected_piece.0 = Some(piece_id);
+    selected_square.0 = squares
+        .iter()
+        .find_map(|(entity, square)| (*square == target).then(|| entity));
+    change_state(&mut game_state, GameState::TargetSquareSelected);
+}
+
+fn find_move(
+    text: &str,
+    turn: PieceColour,
+    all_valid_moves: &AllValidMoves,
+    pieces: &[(Entity, Piece)],
+) -> Result<(Entity, Square, Option<PieceKind>), String> {
+    let Some((from, to, promotion)) = notation::parse_move(text) else { return Err("it isn't a move in coordinate notation, e.g. e2e4 or e7e8q".to_string()) };
+    let Some((piece_id, piece)) = pieces.iter().find(|(_, piece)| piece.square == from && piece.colour == turn) else { return Err(format!("there's no {} piece on {}", turn, from)) };
+
+    if promotion.is_some()
+        && (piece.kind != PieceKind::Pawn || to.rank != piece.colour.final_rank())
+    {
+        return Err(format!(
+            "the {:?} on {} can't be promoted there",
+            piece.kind, from
+        ));
+    }
+
+    all_valid_moves
+        .get(*piece_id)
+        .iter()
+        .any(|move_| notation::move_coordinates(piece, move_) == text[..4])
+        .then(|| (*piece_id, to, promotion))
+        .ok_or_else(|| format!("the {:?} on {} can't move there", piece.kind, from))
+}
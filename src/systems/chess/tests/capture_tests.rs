@@ -0,0 +1,67 @@
+use crate::model::{BoardScale, Piece, PieceColour, PieceKind, Square};
+use crate::systems::chess::{
+    despawn_taken_pieces, sink_captured_pieces, AnimationSettings, CaptureAnimation, GameState,
+    PlayerTurn, Taken, CAPTURE_ANIMATION_SECONDS,
+};
+use bevy::prelude::*;
+
+fn setup() -> (World, SystemStage) {
+    let mut world = World::new();
+
+    world.insert_resource(PlayerTurn(PieceColour::White));
+    world.insert_resource(State::new(GameState::MovingPiece));
+    world.insert_resource(AnimationSettings::default());
+    world.insert_resource(BoardScale::default());
+    world.insert_resource(Time::default());
+
+    let mut update_stage = SystemStage::parallel();
+    update_stage.add_system_set(State::<GameState>::get_driver());
+    update_stage.add_system(despawn_taken_pieces.system());
+    update_stage.add_system(sink_captured_pieces.system());
+
+    (world, update_stage)
+}
+
+fn spawn_taken_piece(world: &mut World) -> Entity {
+    let square = Square::new(6, 3);
+
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::Pawn, square))
+        .insert(Transform::from_translation(
+            square.to_translation(BoardScale::default()),
+        ))
+        .insert(Taken)
+        .id()
+}
+
+#[test]
+fn a_taken_piece_should_only_be_despawned_once_it_has_finished_sinking() {
+    let (mut world, mut stage) = setup();
+    let taken = spawn_taken_piece(&mut world);
+
+    stage.run(&mut world);
+
+    assert!(world.get_entity(taken).is_some());
+    assert!(world.get::<Piece>(taken).is_none());
+    assert!(world.get::<CaptureAnimation>(taken).is_some());
+
+    stage.run(&mut world);
+    assert!(world.get_entity(taken).is_some());
+
+    world.get_mut::<CaptureAnimation>(taken).unwrap().elapsed = CAPTURE_ANIMATION_SECONDS;
+    stage.run(&mut world);
+
+    assert!(world.get_entity(taken).is_none());
+}
+
+#[test]
+fn a_taken_piece_should_be_despawned_immediately_if_animations_are_instant() {
+    let (mut world, mut stage) = setup();
+    world.insert_resource(AnimationSettings { instant: true });
+    let taken = spawn_taken_piece(&mut world);
+
+    stage.run(&mut world);
+
+    assert!(world.get_entity(taken).is_none());
+}
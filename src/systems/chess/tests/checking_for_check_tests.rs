@@ -1,14 +1,18 @@
-use crate::model::{AllValidMoves, CastlingData, Move, Piece, PieceColour, PieceKind, SpecialMoveData, Square};
-use crate::systems::chess::{calculate_all_moves, GameState, PlayerTurn};
+use crate::model::{position_status, AllValidMoves, BoardState, CastlingData, CheckState, LastPawnDoubleStep, Move, MoveKind, Piece, PieceColour, PieceKind, PositionStatus, SpecialMoveData, Square};
+use crate::systems::chess::{calculate_all_moves, GameState, MatingMove, PerformanceStats, PlayerTurn};
 use bevy::prelude::*;
 
 fn setup() -> (World, SystemStage) {
     let mut world = World::new();
 
     world.insert_resource(AllValidMoves::default());
+    world.insert_resource(CheckState::default());
+    world.insert_resource::<Option<MatingMove>>(None);
+    world.insert_resource(PerformanceStats::default());
     world.insert_resource(PlayerTurn(PieceColour::Black));
     world.insert_resource(State::new(GameState::NothingSelected));
     world.insert_resource(SpecialMoveData {
+        last_move: None,
         last_pawn_double_step: None,
         black_castling_data: CastlingData {
             king_moved: true,
@@ -550,6 +554,120 @@ fn should_not_be_able_to_move_a_piece_to_take_a_second_piece_with_the_king_in_ch
     );
 }
 
+#[test]
+fn should_not_be_able_to_take_a_piece_giving_check_with_a_piece_pinned_along_a_different_file() {
+    let (mut world, mut update_stage) = setup();
+
+    let king_id = world
+        .spawn()
+        .insert(Piece {
+            kind: PieceKind::King,
+            colour: PieceColour::Black,
+            square: (7, 4).into(),
+        })
+        .id();
+
+    // has the king in check
+    world.spawn().insert(Piece {
+        kind: PieceKind::Knight,
+        colour: PieceColour::White,
+        square: (5, 3).into(),
+    });
+
+    // could move sideways to take the knight, but is pinned to the king along the file
+    let rook_id = world
+        .spawn()
+        .insert(Piece {
+            kind: PieceKind::Rook,
+            colour: PieceColour::Black,
+            square: (5, 4).into(),
+        })
+        .id();
+
+    // pinning the rook
+    world.spawn().insert(Piece {
+        kind: PieceKind::Rook,
+        colour: PieceColour::White,
+        square: (2, 4).into(),
+    });
+
+    update_stage.run(&mut world);
+
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::NothingSelected
+    );
+
+    let all_valid_moves = world.get_resource::<AllValidMoves>().unwrap();
+    assert!(all_valid_moves.get(rook_id).is_empty());
+    assert_eq!(
+        all_valid_moves.get(king_id),
+        &vec![
+            Move::standard((6, 3).into()),
+            Move::standard((6, 4).into()),
+            Move::standard((7, 3).into()),
+            Move::standard((7, 5).into())
+        ]
+    );
+}
+
+#[test]
+fn should_not_be_able_to_take_a_piece_giving_check_with_a_piece_pinned_along_a_diagonal() {
+    let (mut world, mut update_stage) = setup();
+
+    let king_id = world
+        .spawn()
+        .insert(Piece {
+            kind: PieceKind::King,
+            colour: PieceColour::Black,
+            square: (7, 4).into(),
+        })
+        .id();
+
+    // has the king in check
+    world.spawn().insert(Piece {
+        kind: PieceKind::Knight,
+        colour: PieceColour::White,
+        square: (6, 2).into(),
+    });
+
+    // could move along the rank to take the knight, but is pinned to the king along the diagonal
+    let rook_id = world
+        .spawn()
+        .insert(Piece {
+            kind: PieceKind::Rook,
+            colour: PieceColour::Black,
+            square: (6, 3).into(),
+        })
+        .id();
+
+    // pinning the rook
+    world.spawn().insert(Piece {
+        kind: PieceKind::Bishop,
+        colour: PieceColour::White,
+        square: (4, 1).into(),
+    });
+
+    update_stage.run(&mut world);
+
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::NothingSelected
+    );
+
+    let all_valid_moves = world.get_resource::<AllValidMoves>().unwrap();
+    assert!(all_valid_moves.get(rook_id).is_empty());
+    assert_eq!(
+        all_valid_moves.get(king_id),
+        &vec![
+            Move::standard((6, 4).into()),
+            Move::standard((6, 5).into()),
+            Move::standard((7, 3).into()),
+            Move::standard((7, 5).into())
+        ]
+    );
+}
+
 #[test]
 fn should_detect_checkmate_when_multiple_pieces_have_the_king_in_check_even_when_they_can_both_be_taken(
 ) {
@@ -719,6 +837,45 @@ fn should_detect_stalemate_when_the_current_player_cannot_make_any_moves_but_is_
     assert_eq!(state.current(), &GameState::Stalemate(PieceColour::Black));
 }
 
+#[test]
+fn should_detect_stalemate_when_the_only_pseudo_legal_move_is_a_pawn_pinned_along_a_rank() {
+    let (mut world, mut update_stage) = setup();
+
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, Square::new(4, 0)));
+    let pawn = world
+        .spawn()
+        .insert(Piece::black(PieceKind::Pawn, Square::new(4, 1)))
+        .id();
+
+    // pushing the pawn would expose the king to the rook
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::Rook, Square::new(4, 7)));
+    // cover every square the king could step to, without putting it in check
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, Square::new(2, 0)));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::Knight, Square::new(7, 1)));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::Knight, Square::new(7, 2)));
+
+    update_stage.run(&mut world);
+
+    let state = world.get_resource::<State<GameState>>().unwrap();
+    assert_eq!(state.current(), &GameState::Stalemate(PieceColour::Black));
+    assert!(!world.get_resource::<CheckState>().unwrap().is_check());
+    assert!(world
+        .get_resource::<AllValidMoves>()
+        .unwrap()
+        .get(pawn)
+        .is_empty());
+}
+
 // see bug screenshots 1
 #[test]
 fn fix_bug_1_incorrectly_restricted_move_calculations() {
@@ -825,3 +982,334 @@ fn fix_bug_3_illegal_king_move_allowed() {
         ]
     );
 }
+
+#[test]
+fn a_king_in_the_corner_should_not_be_able_to_take_a_piece_defended_along_a_diagonal() {
+    let (mut world, mut update_stage) = setup();
+
+    let king_id = world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, Square::new(0, 0)))
+        .id();
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::Pawn, Square::new(1, 1)));
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::Bishop, Square::new(2, 2)));
+
+    world.insert_resource(PlayerTurn(PieceColour::White));
+    update_stage.run(&mut world);
+
+    let all_valid_moves = world.get_resource::<AllValidMoves>().unwrap();
+    assert!(!all_valid_moves.contains(king_id, (1, 1).into()));
+    assert_eq!(all_valid_moves.get(king_id).len(), 2);
+}
+
+#[test]
+fn a_king_in_the_corner_should_not_be_able_to_take_a_piece_defended_by_a_pawn() {
+    let (mut world, mut update_stage) = setup();
+
+    let king_id = world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, Square::new(0, 0)))
+        .id();
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::Pawn, Square::new(1, 1)));
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::Pawn, Square::new(2, 2)));
+
+    world.insert_resource(PlayerTurn(PieceColour::White));
+    update_stage.run(&mut world);
+
+    let all_valid_moves = world.get_resource::<AllValidMoves>().unwrap();
+    assert!(!all_valid_moves.contains(king_id, (1, 1).into()));
+    assert_eq!(all_valid_moves.get(king_id).len(), 2);
+}
+
+#[test]
+fn a_king_in_the_corner_should_not_be_able_to_take_a_piece_defended_by_a_pawn_on_the_edge_of_the_board() {
+    let (mut world, mut update_stage) = setup();
+
+    let king_id = world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, Square::new(0, 0)))
+        .id();
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::Pawn, Square::new(1, 1)));
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::Pawn, Square::new(2, 0)));
+
+    world.insert_resource(PlayerTurn(PieceColour::White));
+    update_stage.run(&mut world);
+
+    let all_valid_moves = world.get_resource::<AllValidMoves>().unwrap();
+    assert!(!all_valid_moves.contains(king_id, (1, 1).into()));
+    assert!(all_valid_moves.contains(king_id, (0, 1).into()));
+}
+
+#[test]
+fn should_record_every_piece_attacking_the_king_during_double_check() {
+    let (mut world, mut update_stage) = setup();
+
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, Square::new(7, 4)));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, Square::new(0, 0)));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::Rook, Square::new(1, 4)));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::Knight, Square::new(5, 5)));
+
+    update_stage.run(&mut world);
+
+    let state = world.get_resource::<State<GameState>>().unwrap();
+    assert_eq!(state.current(), &GameState::NothingSelected);
+
+    let check_state = world.get_resource::<CheckState>().unwrap();
+    let attack_lines = check_state.attack_lines();
+    assert_eq!(attack_lines.len(), 2);
+    assert!(attack_lines.contains(&(Square::new(1, 4), Square::new(7, 4))));
+    assert!(attack_lines.contains(&(Square::new(5, 5), Square::new(7, 4))));
+}
+
+#[test]
+fn should_not_record_any_attackers_when_the_king_is_not_in_check() {
+    let (mut world, mut update_stage) = setup();
+
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, Square::new(7, 4)));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, Square::new(0, 0)));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::Rook, Square::new(1, 3)));
+
+    update_stage.run(&mut world);
+
+    let check_state = world.get_resource::<CheckState>().unwrap();
+    assert!(!check_state.is_check());
+    assert!(check_state.attack_lines().is_empty());
+}
+
+#[test]
+fn should_surface_the_only_legal_move_as_a_forced_move() {
+    let (mut world, mut update_stage) = setup();
+
+    let king_id = world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, Square::new(7, 7)))
+        .id();
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, Square::new(0, 0)));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::Rook, Square::new(7, 0)));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::Rook, Square::new(5, 6)));
+
+    update_stage.run(&mut world);
+
+    let all_valid_moves = world.get_resource::<AllValidMoves>().unwrap();
+    assert_eq!(all_valid_moves.total_move_count(), 1);
+    assert_eq!(
+        all_valid_moves.forced_move(),
+        Some((king_id, Move::standard((6, 7).into())))
+    );
+}
+
+#[test]
+fn should_only_record_the_move_calculation_time_while_the_performance_overlay_is_enabled() {
+    let (mut world, mut update_stage) = setup();
+
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, Square::new(7, 7)));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, Square::new(0, 0)));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::Rook, Square::new(3, 3)));
+
+    update_stage.run(&mut world);
+    let stats = world.get_resource::<PerformanceStats>().unwrap();
+    assert!(stats.last_move_calculation.is_none());
+
+    world.insert_resource(PerformanceStats {
+        enabled: true,
+        last_move_calculation: None,
+    });
+    update_stage.run(&mut world);
+
+    let stats = world.get_resource::<PerformanceStats>().unwrap();
+    assert!(stats.last_move_calculation.is_some());
+}
+
+#[test]
+fn a_player_without_a_king_should_still_be_able_to_move_without_ever_being_in_check() {
+    let (mut world, mut update_stage) = setup();
+
+    let rook = world
+        .spawn()
+        .insert(Piece::black(PieceKind::Rook, Square::new(7, 0)))
+        .id();
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, Square::new(0, 4)));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::Queen, Square::new(0, 0)));
+
+    update_stage.run(&mut world);
+
+    let state = world.get_resource::<State<GameState>>().unwrap();
+    assert_eq!(state.current(), &GameState::NothingSelected);
+    assert!(!world.get_resource::<CheckState>().unwrap().is_check());
+
+    let all_valid_moves = world.get_resource::<AllValidMoves>().unwrap();
+    // 7 squares along the rank, and 7 down the file including taking the Queen
+    assert_eq!(all_valid_moves.get(rook).len(), 14);
+    assert!(all_valid_moves.contains(rook, Square::new(0, 0)));
+}
+
+/// runs `calculate_all_moves` on the position, and checks `position_status` agrees with it
+fn assert_status_matches_ecs(pieces: &[Piece], turn: PieceColour, expected: PositionStatus) {
+    let (mut world, mut update_stage) = setup();
+    world.insert_resource(PlayerTurn(turn));
+    let pieces = pieces
+        .iter()
+        .map(|piece| (world.spawn().insert(*piece).id(), *piece))
+        .collect::<Vec<_>>();
+    update_stage.run(&mut world);
+
+    let ecs_status = match world.get_resource::<State<GameState>>().unwrap().current() {
+        GameState::Checkmate(colour) if *colour == turn => PositionStatus::Checkmate,
+        GameState::Stalemate(colour) if *colour == turn => PositionStatus::Stalemate,
+        GameState::NothingSelected if world.get_resource::<CheckState>().unwrap().is_check() => {
+            PositionStatus::Check
+        }
+        GameState::NothingSelected => PositionStatus::Ongoing,
+        other => panic!("unexpected state {:?}", other),
+    };
+    assert_eq!(ecs_status, expected);
+
+    let board = pieces
+        .iter()
+        .map(|(_, piece)| piece)
+        .collect::<BoardState>();
+    let special_move_data = world.get_resource::<SpecialMoveData>().unwrap();
+    assert_eq!(
+        position_status(&board, &pieces, turn, special_move_data),
+        expected
+    );
+}
+
+#[test]
+fn the_position_status_should_match_the_ecs_for_checkmate() {
+    assert_status_matches_ecs(
+        &[
+            Piece::black(PieceKind::King, (7, 7).into()),
+            Piece::white(PieceKind::Queen, (6, 6).into()),
+            Piece::white(PieceKind::King, (5, 5).into()),
+        ],
+        PieceColour::Black,
+        PositionStatus::Checkmate,
+    );
+}
+
+#[test]
+fn the_position_status_should_match_the_ecs_for_stalemate() {
+    assert_status_matches_ecs(
+        &[
+            Piece::black(PieceKind::King, (7, 0).into()),
+            Piece::white(PieceKind::Queen, (5, 1).into()),
+            Piece::white(PieceKind::King, (0, 4).into()),
+        ],
+        PieceColour::Black,
+        PositionStatus::Stalemate,
+    );
+}
+
+#[test]
+fn the_position_status_should_match_the_ecs_for_ongoing_games() {
+    assert_status_matches_ecs(
+        &[
+            Piece::black(PieceKind::King, (7, 4).into()),
+            Piece::white(PieceKind::Rook, (0, 4).into()),
+            Piece::white(PieceKind::King, (0, 0).into()),
+        ],
+        PieceColour::Black,
+        PositionStatus::Check,
+    );
+    assert_status_matches_ecs(
+        &Piece::starting_position(),
+        PieceColour::White,
+        PositionStatus::Ongoing,
+    );
+}
+
+#[test]
+fn the_position_status_should_match_the_ecs_when_the_only_escape_is_en_passant() {
+    let (mut world, mut update_stage) = setup();
+    world.insert_resource(PlayerTurn(PieceColour::White));
+
+    // the b5 Pawn has just double stepped to check the a4 King, and can only be taken en passant
+    let pieces = [
+        Piece::white(PieceKind::King, (3, 0).into()),
+        Piece::white(PieceKind::Pawn, (4, 0).into()),
+        Piece::black(PieceKind::Pawn, (4, 1).into()),
+        Piece::black(PieceKind::Pawn, (5, 2).into()),
+        Piece::black(PieceKind::Rook, (0, 1).into()),
+        Piece::black(PieceKind::Rook, (2, 7).into()),
+        Piece::black(PieceKind::King, (7, 7).into()),
+    ]
+    .map(|piece| (world.spawn().insert(piece).id(), piece));
+    let (white_pawn, _) = pieces[1];
+    let (black_pawn, _) = pieces[2];
+    world
+        .get_resource_mut::<SpecialMoveData>()
+        .unwrap()
+        .last_pawn_double_step = Some(LastPawnDoubleStep {
+        pawn_id: black_pawn,
+        square: (4, 1).into(),
+    });
+    update_stage.run(&mut world);
+
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::NothingSelected
+    );
+    assert!(world.get_resource::<CheckState>().unwrap().is_check());
+    let all_valid_moves = world.get_resource::<AllValidMoves>().unwrap();
+    assert_eq!(all_valid_moves.total_move_count(), 1);
+    assert!(matches!(
+        all_valid_moves.get(white_pawn)[..],
+        [Move {
+            kind: MoveKind::EnPassant { .. },
+            ..
+        }]
+    ));
+
+    let board = pieces
+        .iter()
+        .map(|(_, piece)| piece)
+        .collect::<BoardState>();
+    let special_move_data = world.get_resource::<SpecialMoveData>().unwrap();
+    assert_eq!(
+        position_status(&board, &pieces, PieceColour::White, special_move_data),
+        PositionStatus::Check
+    );
+}
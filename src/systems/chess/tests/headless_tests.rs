@@ -0,0 +1,186 @@
+use crate::model::{BoardState, Piece, PieceColour, PieceKind};
+use crate::systems::chess::headless::HeadlessGame;
+use crate::systems::chess::scripted_moves::{ScriptError, ScriptedMoves};
+use crate::systems::chess::GameState;
+
+#[test]
+fn random_moves_should_be_played_until_the_ply_limit_unless_the_game_ends() {
+    (1..=5).for_each(|seed| {
+        let mut game = HeadlessGame::new();
+        let played = game.play_random_moves(seed, 40);
+
+        if game.state().is_game_over() {
+            assert!(played <= 40, "seed {}", seed);
+        } else {
+            assert_eq!(played, 40, "seed {}", seed);
+            assert_eq!(game.state(), &GameState::NothingSelected, "seed {}", seed);
+        }
+
+        let pieces = game.pieces();
+        PieceColour::all().into_iter().for_each(|colour| {
+            let kings = pieces
+                .iter()
+                .filter(|piece| piece.colour == colour && piece.kind == PieceKind::King)
+                .count();
+            assert_eq!(kings, 1, "seed {}", seed);
+        });
+    });
+}
+
+#[test]
+fn the_same_seed_should_play_the_same_game() {
+    let mut first = HeadlessGame::new();
+    let mut second = HeadlessGame::new();
+    first.play_random_moves(7, 40);
+    second.play_random_moves(7, 40);
+
+    let mut first_pieces = first.pieces();
+    let mut second_pieces = second.pieces();
+    first_pieces.sort();
+    second_pieces.sort();
+
+    assert_eq!(first_pieces, second_pieces);
+    assert_ne!(first_pieces, {
+        let mut start = Piece::starting_position();
+        start.sort();
+        start
+    });
+}
+
+#[test]
+fn no_moves_should_be_played_once_the_game_is_over() {
+    let mut game = HeadlessGame::from_position(
+        vec![
+            Piece::black(PieceKind::King, (7, 0).into()),
+            Piece::white(PieceKind::Queen, (5, 1).into()),
+            Piece::white(PieceKind::King, (0, 4).into()),
+        ],
+        PieceColour::Black,
+    );
+
+    assert_eq!(game.state(), &GameState::Stalemate(PieceColour::Black));
+    assert_eq!(game.play_random_moves(1, 40), 0);
+    assert_eq!(game.pieces().len(), 3);
+}
+
+#[test]
+fn moves_read_from_a_file_should_all_be_played_in_order() {
+    let path = std::env::temp_dir().join("bevy_chess_scripted_moves_test.txt");
+    std::fs::write(&path, "e2e4 e7e5\ng1f3 b8c6\nf1c4 g8f6\ne1g1\n").unwrap();
+    let moves = ScriptedMoves::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut game = HeadlessGame::new();
+    assert_eq!(game.play_scripted_moves(moves), Ok(7));
+
+    let pieces = game.pieces();
+    [
+        Piece::white(PieceKind::Pawn, (3, 4).into()),
+        Piece::white(PieceKind::Knight, (2, 5).into()),
+        Piece::white(PieceKind::Bishop, (3, 2).into()),
+        Piece::white(PieceKind::King, (0, 6).into()),
+        Piece::white(PieceKind::Rook, (0, 5).into()),
+        Piece::black(PieceKind::Pawn, (4, 4).into()),
+        Piece::black(PieceKind::Knight, (5, 2).into()),
+        Piece::black(PieceKind::Knight, (5, 5).into()),
+    ]
+    .into_iter()
+    .for_each(|piece| assert!(pieces.contains(&piece), "{:?}", piece));
+    assert_eq!(pieces.len(), 32);
+    assert_eq!(game.state(), &GameState::NothingSelected);
+}
+
+#[test]
+fn the_first_move_that_cannot_be_played_should_be_reported() {
+    let mut game = HeadlessGame::new();
+    let played = game.play_scripted_moves(ScriptedMoves::new("e2e4 e7e5 e1e3 d2d4"));
+
+    assert_eq!(
+        played,
+        Err(ScriptError {
+            move_number: 3,
+            move_: "e1e3".to_string(),
+            reason: "the King on e1 can't move there".to_string(),
+        })
+    );
+    // nothing after the illegal move is played either
+    assert!(game
+        .pieces()
+        .contains(&Piece::white(PieceKind::Pawn, (1, 3).into())));
+
+    let mut game = HeadlessGame::new();
+    let played = game.play_scripted_moves(ScriptedMoves::new("e7e5"));
+    assert_eq!(played.unwrap_err().reason, "there's no White piece on e7");
+
+    let mut game = HeadlessGame::new();
+    let played = game.play_scripted_moves(ScriptedMoves::new("e2e4 Nf6"));
+    assert_eq!(played.unwrap_err().move_number, 2);
+
+    // Fool's Mate, and then one more
+    let mut game = HeadlessGame::new();
+    let played = game.play_scripted_moves(ScriptedMoves::new("f2f3 e7e5 g2g4 d8h4 a2a3"));
+    assert_eq!(played.unwrap_err().reason, "the game is already over");
+    assert_eq!(game.state(), &GameState::Checkmate(PieceColour::White));
+}
+
+#[test]
+fn a_scripted_promotion_should_promote_to_the_chosen_piece() {
+    let position = || {
+        vec![
+            Piece::white(PieceKind::King, (0, 7).into()),
+            Piece::white(PieceKind::Pawn, (6, 0).into()),
+            Piece::black(PieceKind::King, (7, 7).into()),
+        ]
+    };
+
+    let mut game = HeadlessGame::from_position(position(), PieceColour::White);
+    assert_eq!(
+        game.play_scripted_moves(ScriptedMoves::new("a7a8n h8g7 a8b6")),
+        Ok(3)
+    );
+    assert!(game
+        .pieces()
+        .contains(&Piece::white(PieceKind::Knight, (5, 1).into())));
+
+    let mut game = HeadlessGame::from_position(position(), PieceColour::White);
+    let played = game.play_scripted_moves(ScriptedMoves::new("h1g1q"));
+    assert_eq!(
+        played.unwrap_err().reason,
+        "the King on h1 can't be promoted there"
+    );
+
+    let mut game = HeadlessGame::from_position(position(), PieceColour::White);
+    let played = game.play_scripted_moves(ScriptedMoves::new("a7a8k"));
+    assert_eq!(played.unwrap_err().move_number, 1);
+}
+
+#[test]
+fn a_game_should_be_replayed_from_its_coordinate_string() {
+    // with castling, and White taking the f-pawn en passant
+    let moves = "e2e4 d7d5 e4e5 f7f5 e5f6 g8f6 g1f3 b8c6 f1c4 e7e6 e1g1";
+    let mut game = HeadlessGame::new();
+    assert_eq!(game.play_scripted_moves(ScriptedMoves::new(moves)), Ok(11));
+    assert_eq!(game.move_log().to_coordinate_string(), moves);
+
+    (1..=5).for_each(|seed| {
+        let mut game = HeadlessGame::new();
+        game.play_random_moves(seed, 80);
+        let moves = game.move_log().to_coordinate_string();
+
+        let mut replayed = HeadlessGame::from_coordinate_string(&moves).unwrap();
+        let mut pieces = game.pieces();
+        let mut replayed_pieces = replayed.pieces();
+        assert_eq!(
+            replayed_pieces.iter().collect::<BoardState>(),
+            pieces.iter().collect::<BoardState>(),
+            "seed {}",
+            seed
+        );
+
+        pieces.sort();
+        replayed_pieces.sort();
+        assert_eq!(replayed_pieces, pieces, "seed {}", seed);
+        assert_eq!(replayed.state(), game.state(), "seed {}", seed);
+        assert_eq!(replayed.move_log().0, game.move_log().0, "seed {}", seed);
+    });
+}
@@ -1,4 +1,5 @@
 use super::*;
+use bevy::app::Events;
 
 #[test]
 fn moving_pieces_in_xz_should_gently_ease_out_then_gently_ease_in() {
@@ -25,6 +26,26 @@ fn moving_pieces_in_xz_should_gently_ease_out_then_gently_ease_in() {
     );
 }
 
+#[test]
+fn every_move_easing_should_start_and_finish_on_a_square_but_differ_in_between() {
+    MoveEasing::all().into_iter().for_each(|easing| {
+        assert_eq!(easing.apply(0.0), 0.0, "{:?}", easing);
+        assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{:?}", easing);
+    });
+
+    let midway = MoveEasing::all().map(|easing| easing.apply(0.25));
+    assert_eq!(midway[0], 0.25);
+    midway.iter().enumerate().for_each(|(index, progress)| {
+        assert!(
+            midway[..index]
+                .iter()
+                .all(|other| (other - progress).abs() > 0.01),
+            "{:?}",
+            midway
+        );
+    });
+}
+
 #[test]
 fn moving_pieces_in_y_should_form_a_parabola() {
     let samples = [0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0]
@@ -49,3 +70,73 @@ fn moving_pieces_in_y_should_form_a_parabola() {
         ]
     );
 }
+
+#[test]
+fn a_castling_king_should_slide_and_land_at_the_same_time_as_the_rook() {
+    let (king_move, rook_move) = MovePiece::castle(
+        ((0, 4).into(), (0, 2).into()),
+        ((0, 0).into(), (0, 3).into()),
+        BoardScale::default(),
+    );
+
+    assert_eq!(king_move.max_height, 0.0);
+    assert!(rook_move.max_height > 0.0);
+    assert_eq!(king_move.duration, rook_move.duration);
+}
+
+#[test]
+fn the_turn_should_not_end_until_both_castling_pieces_have_arrived() {
+    let mut world = World::new();
+    world.insert_resource(PlayerTurn(PieceColour::White));
+    world.insert_resource(State::new(GameState::MovingPiece));
+    world.insert_resource(PromotedPawn::default());
+    world.insert_resource(Time::default());
+    world.insert_resource(MoveEasing::default());
+    world.insert_resource(Events::<PawnPromotable>::default());
+
+    let scale = BoardScale::default();
+    let (king_move, rook_move) = MovePiece::castle(
+        ((0, 4).into(), (0, 6).into()),
+        ((0, 7).into(), (0, 5).into()),
+        scale,
+    );
+    let king = world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, (0, 4).into()))
+        // the King has already arrived, but the Rook hasn't moved yet
+        .insert(Transform::from_translation(king_move.to))
+        .insert(king_move)
+        .id();
+    let rook = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Rook, (0, 7).into()))
+        .insert(Transform::from_translation(rook_move.from))
+        .insert(rook_move)
+        .id();
+
+    let mut stage = SystemStage::parallel();
+    stage.add_system_set(State::<GameState>::get_driver());
+    stage.add_system_set(
+        SystemSet::on_update(GameState::MovingPiece).with_system(translate_moved_pieces.system()),
+    );
+    stage.run(&mut world);
+
+    assert_eq!(world.get::<Piece>(king).unwrap().square, (0, 6).into());
+    assert_eq!(world.get_resource::<PlayerTurn>().unwrap().0, PieceColour::White);
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::MovingPiece
+    );
+
+    let rook_target = world.get::<MovePiece>(rook).unwrap().to;
+    world.get_mut::<Transform>(rook).unwrap().translation = rook_target;
+    stage.run(&mut world);
+
+    assert_eq!(world.get::<Piece>(king).unwrap().square, (0, 6).into());
+    assert_eq!(world.get::<Piece>(rook).unwrap().square, (0, 5).into());
+    assert_eq!(world.get_resource::<PlayerTurn>().unwrap().0, PieceColour::Black);
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::NothingSelected
+    );
+}
@@ -0,0 +1,406 @@
+use crate::model::{
+    AllValidMoves, BoardScale, CheckState, Piece, PieceColour, PieceKind, SpecialMoveData, Square,
+};
+use crate::systems::chess::game_set_up::{PieceMaterials, PieceMeshes};
+use crate::systems::chess::scripted_moves::ScriptedMoves;
+use crate::systems::chess::{
+    calculate_all_moves, grow_promoted_pieces, next_promotion, previous_promotion,
+    promote_pawn_at_final_rank, translate_moved_pieces, AnimationSettings, GameState, MatingMove,
+    MoveEasing, MoveLog, MovePiece, PawnPromotable, PerformanceStats, PlayerTurn, PromotedPawn,
+    PromotionAnimation, PromotionMode, PromotionSettings, PROMOTION_ANIMATION_SECONDS,
+};
+use bevy::app::Events;
+use bevy::prelude::*;
+
+fn setup() -> (World, SystemStage) {
+    let mut world = World::new();
+
+    world.insert_resource(PlayerTurn(PieceColour::White));
+    world.insert_resource(State::new(GameState::PawnPromotion));
+    world.insert_resource(PromotedPawn::default());
+    world.insert_resource(MoveLog::default());
+    world.insert_resource(ScriptedMoves::default());
+    world.insert_resource(BoardScale::default());
+    world.insert_resource(AnimationSettings::default());
+    world.insert_resource(PromotionSettings::default());
+    world.insert_resource(Input::<KeyCode>::default());
+    world.insert_resource(Time::default());
+    world.insert_resource(PieceMeshes::placeholder());
+    world.insert_resource(PieceMaterials {
+        white: Handle::default(),
+        black: Handle::default(),
+        white_preview: Handle::default(),
+        black_preview: Handle::default(),
+        white_selected: Handle::default(),
+        black_selected: Handle::default(),
+    });
+
+    let mut update_stage = SystemStage::parallel();
+    update_stage.add_system_set(State::<GameState>::get_driver());
+    update_stage.add_system_set(
+        SystemSet::on_update(GameState::PawnPromotion)
+            .with_system(promote_pawn_at_final_rank.system()),
+    );
+    update_stage.add_system(grow_promoted_pieces.system());
+
+    (world, update_stage)
+}
+
+fn press(world: &mut World, key: KeyCode) {
+    world
+        .get_resource_mut::<Input<KeyCode>>()
+        .unwrap()
+        .press(key);
+}
+
+fn promoted_piece(world: &World) -> Entity {
+    world
+        .get_resource::<PromotedPawn>()
+        .unwrap()
+        .0
+        .expect("should have a promoted piece")
+}
+
+#[test]
+fn a_promoted_piece_should_grow_into_place_and_then_stop_animating() {
+    let (mut world, mut stage) = setup();
+
+    let pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (7, 0).into()))
+        .id();
+    world.insert_resource(PromotedPawn(Some(pawn)));
+
+    press(&mut world, KeyCode::Left);
+    stage.run(&mut world);
+
+    let queen = promoted_piece(&world);
+    assert_ne!(queen, pawn);
+    assert_eq!(world.get::<Piece>(queen).unwrap().kind, PieceKind::Queen);
+    assert!(world.get::<PromotionAnimation>(queen).is_some());
+
+    world.get_resource_mut::<Input<KeyCode>>().unwrap().clear();
+    world
+        .get_mut::<PromotionAnimation>(queen)
+        .unwrap()
+        .elapsed = PROMOTION_ANIMATION_SECONDS;
+    stage.run(&mut world);
+
+    assert!(world.get::<PromotionAnimation>(queen).is_none());
+    assert_ne!(world.get::<Transform>(queen).unwrap().scale, Vec3::ZERO);
+}
+
+#[test]
+fn a_promoted_piece_should_not_be_animated_if_animations_are_instant() {
+    let (mut world, mut stage) = setup();
+
+    let pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (7, 0).into()))
+        .id();
+    world.insert_resource(PromotedPawn(Some(pawn)));
+    world.insert_resource(AnimationSettings { instant: true });
+
+    press(&mut world, KeyCode::Right);
+    stage.run(&mut world);
+
+    let knight = promoted_piece(&world);
+    assert_eq!(world.get::<Piece>(knight).unwrap().kind, PieceKind::Knight);
+    assert!(world.get::<PromotionAnimation>(knight).is_none());
+}
+
+#[test]
+fn a_promoted_piece_should_keep_the_pawns_colour_whoevers_turn_it_is() {
+    let (mut world, mut stage) = setup();
+
+    let pawn = world
+        .spawn()
+        .insert(Piece::black(PieceKind::Pawn, (0, 3).into()))
+        .id();
+    world.insert_resource(PromotedPawn(Some(pawn)));
+    // as if the turn had already been handed over to White
+    world.insert_resource(PlayerTurn(PieceColour::White));
+
+    press(&mut world, KeyCode::Left);
+    stage.run(&mut world);
+
+    let queen = promoted_piece(&world);
+    assert_eq!(
+        world.get::<Piece>(queen).unwrap(),
+        &Piece::black(PieceKind::Queen, (0, 3).into())
+    );
+}
+
+#[test]
+fn a_player_who_always_queens_should_not_be_prompted_to_choose_a_promotion() {
+    let (mut world, mut stage) = setup();
+
+    let mut promotion_settings = world.get_resource_mut::<PromotionSettings>().unwrap();
+    promotion_settings.toggle(PieceColour::White);
+    assert_eq!(promotion_settings.mode(PieceColour::White), PromotionMode::AlwaysQueen);
+    assert_eq!(promotion_settings.mode(PieceColour::Black), PromotionMode::Prompt);
+
+    let pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (7, 0).into()))
+        .id();
+    world.insert_resource(PromotedPawn(Some(pawn)));
+
+    // no input at all
+    stage.run(&mut world);
+
+    assert!(world.get::<Piece>(pawn).is_none());
+    let queens = world
+        .query::<&Piece>()
+        .iter(&world)
+        .filter(|piece| **piece == Piece::white(PieceKind::Queen, (7, 0).into()))
+        .count();
+    assert_eq!(queens, 1);
+    assert!(world.get_resource::<PromotedPawn>().unwrap().0.is_none());
+    assert_eq!(world.get_resource::<PlayerTurn>().unwrap().0, PieceColour::Black);
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::NothingSelected
+    );
+}
+
+/// adds `calculate_all_moves` the same way `ChessPlugin` does, so the next player's moves are
+/// calculated as soon as the promotion ends the turn
+fn calculate_moves_on_new_turn(world: &mut World, stage: &mut SystemStage) {
+    world.insert_resource(AllValidMoves::default());
+    world.insert_resource(CheckState::default());
+    world.insert_resource::<Option<MatingMove>>(None);
+    world.insert_resource(SpecialMoveData::default());
+    world.insert_resource(PerformanceStats::default());
+
+    stage.add_system_set(
+        SystemSet::on_enter(GameState::NothingSelected).with_system(calculate_all_moves.system()),
+    );
+}
+
+/// a White pawn promoting on a8, with Black's King on e8 - a Queen gives check along the back rank
+fn spawn_back_rank_promotion(world: &mut World) -> Entity {
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, (0, 4).into()));
+    let pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (7, 0).into()))
+        .id();
+    world.insert_resource(PromotedPawn(Some(pawn)));
+
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, (7, 4).into()))
+        .id()
+}
+
+fn assert_black_is_in_check_from_a8(world: &World, black_king: Entity) {
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::NothingSelected
+    );
+    assert_eq!(
+        world.get_resource::<CheckState>().unwrap(),
+        &CheckState {
+            king_square: Some((7, 4).into()),
+            attackers: vec![(7, 0).into()],
+        }
+    );
+
+    let mut king_moves = world
+        .get_resource::<AllValidMoves>()
+        .unwrap()
+        .get(black_king)
+        .iter()
+        .map(|move_| move_.target_square)
+        .collect::<Vec<_>>();
+    king_moves.sort();
+    // the back rank is covered by the Queen
+    assert_eq!(
+        king_moves,
+        vec![(6, 3).into(), (6, 4).into(), (6, 5).into()]
+    );
+}
+
+#[test]
+fn the_next_players_moves_should_account_for_a_piece_promoted_automatically() {
+    let (mut world, mut stage) = setup();
+    calculate_moves_on_new_turn(&mut world, &mut stage);
+    world
+        .get_resource_mut::<PromotionSettings>()
+        .unwrap()
+        .toggle(PieceColour::White);
+
+    let black_king = spawn_back_rank_promotion(&mut world);
+    stage.run(&mut world);
+
+    assert_black_is_in_check_from_a8(&world, black_king);
+}
+
+#[test]
+fn the_next_players_moves_should_account_for_a_chosen_promotion() {
+    let (mut world, mut stage) = setup();
+    calculate_moves_on_new_turn(&mut world, &mut stage);
+
+    let black_king = spawn_back_rank_promotion(&mut world);
+    press(&mut world, KeyCode::Left);
+    stage.run(&mut world);
+
+    let mut input = world.get_resource_mut::<Input<KeyCode>>().unwrap();
+    input.clear();
+    input.press(KeyCode::Return);
+    stage.run(&mut world);
+
+    assert_black_is_in_check_from_a8(&world, black_king);
+}
+
+#[test]
+fn a_scripted_promotion_should_be_played_without_prompting() {
+    let (mut world, mut stage) = setup();
+    world.insert_resource(MoveLog(vec!["a8".to_string()], vec!["a7a8".to_string()]));
+    world.insert_resource(ScriptedMoves::promoting_to(PieceKind::Knight));
+
+    let pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (7, 0).into()))
+        .id();
+    world.insert_resource(PromotedPawn(Some(pawn)));
+
+    // no input at all
+    stage.run(&mut world);
+
+    let knights = world
+        .query::<&Piece>()
+        .iter(&world)
+        .filter(|piece| **piece == Piece::white(PieceKind::Knight, (7, 0).into()))
+        .count();
+    assert_eq!(knights, 1);
+    let move_log = world.get_resource::<MoveLog>().unwrap();
+    assert_eq!(move_log.0, vec!["a8=N"]);
+    assert_eq!(move_log.to_coordinate_string(), "a7a8n");
+    assert!(world.get_resource::<PromotedPawn>().unwrap().0.is_none());
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::NothingSelected
+    );
+    assert_eq!(
+        world.get_resource_mut::<ScriptedMoves>().unwrap().take_promotion(),
+        None
+    );
+}
+
+#[test]
+fn promotion_options_should_cycle_in_both_directions() {
+    assert_eq!(previous_promotion(PieceKind::Pawn), PieceKind::Queen);
+    assert_eq!(previous_promotion(PieceKind::Queen), PieceKind::Rook);
+    assert_eq!(previous_promotion(PieceKind::Knight), PieceKind::Queen);
+
+    assert_eq!(next_promotion(PieceKind::Pawn), PieceKind::Knight);
+    assert_eq!(next_promotion(PieceKind::Knight), PieceKind::Bishop);
+    assert_eq!(next_promotion(PieceKind::Queen), PieceKind::Knight);
+}
+
+#[test]
+fn promoting_an_unexpected_piece_kind_should_replace_it_with_a_queen() {
+    assert_eq!(previous_promotion(PieceKind::King), PieceKind::Queen);
+    assert_eq!(next_promotion(PieceKind::King), PieceKind::Knight);
+
+    let (mut world, mut stage) = setup();
+
+    let king = world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, (7, 4).into()))
+        .id();
+    world.insert_resource(PromotedPawn(Some(king)));
+
+    // no input at all
+    stage.run(&mut world);
+
+    assert!(world.get::<Piece>(king).is_none());
+    let promoted = promoted_piece(&world);
+    assert_eq!(
+        world.get::<Piece>(promoted).unwrap(),
+        &Piece::white(PieceKind::Queen, (7, 4).into())
+    );
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::PawnPromotion
+    );
+
+    // the Queen is a normal promotion option, so it's left alone until a key is pressed
+    stage.run(&mut world);
+    assert_eq!(promoted_piece(&world), promoted);
+    assert_eq!(world.query::<&Piece>().iter(&world).count(), 1);
+}
+
+#[test]
+fn a_promotion_without_a_pawn_to_promote_should_end_the_turn_instead_of_panicking() {
+    let (mut world, mut stage) = setup();
+
+    let pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (7, 0).into()))
+        .id();
+    world.insert_resource(PromotedPawn(Some(pawn)));
+    world.despawn(pawn);
+
+    press(&mut world, KeyCode::Left);
+    stage.run(&mut world);
+
+    assert!(world.get_resource::<PromotedPawn>().unwrap().0.is_none());
+    assert_eq!(
+        world.get_resource::<PlayerTurn>().unwrap().0,
+        PieceColour::Black
+    );
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::NothingSelected
+    );
+}
+
+#[test]
+fn a_pawn_reaching_the_final_rank_should_send_a_promotable_event() {
+    let mut world = World::new();
+    world.insert_resource(PlayerTurn(PieceColour::White));
+    world.insert_resource(State::new(GameState::MovingPiece));
+    world.insert_resource(BoardScale::default());
+    world.insert_resource(Time::default());
+    world.insert_resource(MoveEasing::default());
+    world.insert_resource(Events::<PawnPromotable>::default());
+
+    let target = Square::new(7, 2);
+    let pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (6, 2).into()))
+        .insert(MovePiece::new((6, 2).into(), target, BoardScale::default()))
+        // already arrived, so the move finishes this frame
+        .insert(Transform::from_translation(
+            target.to_translation(BoardScale::default()),
+        ))
+        .id();
+    world.insert_resource(PromotedPawn(Some(pawn)));
+
+    let mut stage = SystemStage::parallel();
+    stage.add_system_set(State::<GameState>::get_driver());
+    stage.add_system_set(
+        SystemSet::on_update(GameState::MovingPiece).with_system(translate_moved_pieces.system()),
+    );
+    stage.run(&mut world);
+
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::PawnPromotion
+    );
+
+    let events = world.get_resource::<Events<PawnPromotable>>().unwrap();
+    let sent = events.get_reader().iter(events).collect::<Vec<_>>();
+    assert_eq!(
+        sent,
+        vec![&PawnPromotable {
+            entity: pawn,
+            square: target,
+            colour: PieceColour::White,
+        }]
+    );
+}
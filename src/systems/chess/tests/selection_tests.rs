@@ -0,0 +1,371 @@
+use crate::model::{
+    AllValidMoves, BoardScale, CheckState, Move, Piece, PieceColour, PieceKind, SpecialMoveData,
+    Square,
+};
+use crate::systems::chess::game_set_up::{PieceMaterials, PieceMeshes, SquareMaterials};
+use crate::systems::chess::{
+    calculate_all_moves, colour_squares, glow_selected_piece, preview_hovered_move, reset_selected,
+    select_piece, GameState, HighlightCastling, HighlightedSquare, MatingMove, MovePreview,
+    PerformanceStats, PlayerTurn, PreviewMoves, PromotedPawn, SelectedPiece, SelectedSquare,
+    SelectionGlow, ShowCastling, ShowMoves,
+};
+use bevy::prelude::*;
+
+fn setup() -> (World, SystemStage) {
+    let mut world = World::new();
+
+    world.insert_resource(AllValidMoves::default());
+    world.insert_resource(CheckState::default());
+    world.insert_resource::<Option<MatingMove>>(None);
+    world.insert_resource(PerformanceStats::default());
+    world.insert_resource(PlayerTurn(PieceColour::White));
+    world.insert_resource(State::new(GameState::NothingSelected));
+    world.insert_resource(SelectedSquare::default());
+    world.insert_resource(SelectedPiece::default());
+
+    let mut special_move_data = SpecialMoveData::default();
+    special_move_data.white_castling_data.king_moved = true;
+    special_move_data.black_castling_data.king_moved = true;
+    world.insert_resource(special_move_data);
+
+    let mut update_stage = SystemStage::parallel();
+    update_stage.add_system_set(State::<GameState>::get_driver());
+    update_stage.add_system_set(
+        SystemSet::on_update(GameState::NothingSelected).with_system(calculate_all_moves.system()),
+    );
+    update_stage.add_system_set(
+        SystemSet::on_update(GameState::SquareSelected).with_system(select_piece.system()),
+    );
+
+    (world, update_stage)
+}
+
+#[test]
+fn only_the_current_players_pieces_should_have_moves_calculated() {
+    let (mut world, mut update_stage) = setup();
+
+    let white_king = world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, (0, 4).into()))
+        .id();
+    let black_king = world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, (7, 4).into()))
+        .id();
+    let black_pawn = world
+        .spawn()
+        .insert(Piece::black(PieceKind::Pawn, (6, 0).into()))
+        .id();
+
+    update_stage.run(&mut world);
+
+    let all_valid_moves = world.get_resource::<AllValidMoves>().unwrap();
+    assert!(!all_valid_moves.get(white_king).is_empty());
+    assert!(all_valid_moves.get(black_king).is_empty());
+    assert!(all_valid_moves.get(black_pawn).is_empty());
+}
+
+#[test]
+fn selecting_an_opponents_piece_should_not_select_it() {
+    let (mut world, mut update_stage) = setup();
+
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, (0, 4).into()));
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, (7, 4).into()));
+    let black_pawn_square = world.spawn().insert(Square::new(6, 0)).id();
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::Pawn, (6, 0).into()));
+
+    update_stage.run(&mut world);
+
+    world.get_resource_mut::<SelectedSquare>().unwrap().0 = Some(black_pawn_square);
+    world
+        .get_resource_mut::<State<GameState>>()
+        .unwrap()
+        .overwrite_set(GameState::SquareSelected)
+        .unwrap();
+    update_stage.run(&mut world);
+
+    assert!(world.get_resource::<SelectedPiece>().unwrap().0.is_none());
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::NothingSelected
+    );
+}
+
+fn setup_preview() -> (World, SystemStage, Entity) {
+    let mut world = World::new();
+
+    world.insert_resource(PreviewMoves(true));
+    world.insert_resource::<Option<HighlightedSquare>>(None);
+    world.insert_resource(BoardScale::default());
+    world.insert_resource(PieceMeshes::placeholder());
+    world.insert_resource(PieceMaterials {
+        white: Handle::default(),
+        black: Handle::default(),
+        white_preview: Handle::default(),
+        black_preview: Handle::default(),
+        white_selected: Handle::default(),
+        black_selected: Handle::default(),
+    });
+
+    let rook = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Rook, (0, 0).into()))
+        .id();
+    let mut all_valid_moves = AllValidMoves::default();
+    all_valid_moves.insert(rook, vec![Move::standard((3, 0).into())]);
+    world.insert_resource(all_valid_moves);
+    world.insert_resource(SelectedPiece(Some(rook)));
+
+    let mut update_stage = SystemStage::parallel();
+    update_stage.add_system(preview_hovered_move.system());
+
+    (world, update_stage, rook)
+}
+
+fn hover(world: &mut World, square: Option<Square>) {
+    let highlighted = square.map(|square| HighlightedSquare {
+        entity_id: world.spawn().insert(square).id(),
+        previous_material: Handle::default(),
+    });
+    world.insert_resource(highlighted);
+}
+
+fn previewed_squares(world: &mut World) -> Vec<Square> {
+    world
+        .query::<&MovePreview>()
+        .iter(world)
+        .map(|preview| preview.square)
+        .collect()
+}
+
+#[test]
+fn hovering_over_a_legal_target_square_should_preview_the_move() {
+    let (mut world, mut update_stage, rook) = setup_preview();
+
+    hover(&mut world, Some((3, 0).into()));
+    update_stage.run(&mut world);
+
+    assert_eq!(previewed_squares(&mut world), vec![(3, 0).into()]);
+    assert_eq!(world.get_resource::<SelectedPiece>().unwrap().0, Some(rook));
+
+    hover(&mut world, None);
+    update_stage.run(&mut world);
+
+    assert!(previewed_squares(&mut world).is_empty());
+    assert_eq!(world.get_resource::<SelectedPiece>().unwrap().0, Some(rook));
+}
+
+#[test]
+fn hovering_over_an_illegal_target_square_should_not_preview_the_move() {
+    let (mut world, mut update_stage, _) = setup_preview();
+
+    hover(&mut world, Some((3, 3).into()));
+    update_stage.run(&mut world);
+
+    assert!(previewed_squares(&mut world).is_empty());
+}
+
+#[test]
+fn selecting_a_piece_should_show_its_moves_until_it_is_deselected() {
+    let (mut world, mut update_stage) = setup();
+    world.insert_resource::<Option<HighlightedSquare>>(None);
+    update_stage.add_system_set(
+        SystemSet::on_enter(GameState::NothingSelected).with_system(reset_selected.system()),
+    );
+
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, (0, 4).into()));
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, (7, 4).into()));
+    let knight_square = world.spawn().insert(Square::new(0, 1)).id();
+    let knight = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Knight, (0, 1).into()))
+        .id();
+
+    update_stage.run(&mut world);
+
+    world.get_resource_mut::<SelectedSquare>().unwrap().0 = Some(knight_square);
+    world
+        .get_resource_mut::<State<GameState>>()
+        .unwrap()
+        .overwrite_set(GameState::SquareSelected)
+        .unwrap();
+    update_stage.run(&mut world);
+
+    let mut shown_moves = world.get::<ShowMoves>(knight).unwrap().0.clone();
+    shown_moves.sort_by_key(|square| (square.rank, square.file));
+    assert_eq!(
+        shown_moves,
+        vec![Square::new(1, 3), Square::new(2, 0), Square::new(2, 2)]
+    );
+
+    world
+        .get_resource_mut::<State<GameState>>()
+        .unwrap()
+        .overwrite_set(GameState::NothingSelected)
+        .unwrap();
+    update_stage.run(&mut world);
+
+    assert!(world.get::<ShowMoves>(knight).is_none());
+}
+
+#[test]
+fn selecting_a_king_that_can_castle_should_show_the_castling_squares_separately() {
+    let (mut world, mut update_stage) = setup();
+    world.insert_resource(SpecialMoveData::default());
+
+    let king_square = world.spawn().insert(Square::new(0, 4)).id();
+    let king = world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, (0, 4).into()))
+        .id();
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::Rook, (0, 0).into()));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::Rook, (0, 7).into()));
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, (7, 4).into()));
+
+    update_stage.run(&mut world);
+
+    world.get_resource_mut::<SelectedSquare>().unwrap().0 = Some(king_square);
+    world
+        .get_resource_mut::<State<GameState>>()
+        .unwrap()
+        .overwrite_set(GameState::SquareSelected)
+        .unwrap();
+    update_stage.run(&mut world);
+
+    let castling = world.get::<ShowCastling>(king).unwrap();
+    let mut rook_squares = castling.rook_squares.clone();
+    rook_squares.sort_by_key(|square| square.file);
+    let mut king_destinations = castling.king_destinations.clone();
+    king_destinations.sort_by_key(|square| square.file);
+
+    assert_eq!(rook_squares, vec![Square::new(0, 0), Square::new(0, 7)]);
+    assert_eq!(
+        king_destinations,
+        vec![Square::new(0, 2), Square::new(0, 6)]
+    );
+    // the castling moves are still ordinary targets as far as selecting them goes
+    let shown_moves = &world.get::<ShowMoves>(king).unwrap().0;
+    assert!(rook_squares
+        .iter()
+        .all(|square| shown_moves.contains(square)));
+}
+
+#[test]
+fn an_en_passant_target_should_be_highlighted_as_a_capture_even_though_it_is_empty() {
+    let mut world = World::new();
+    world.insert_resource::<Option<HighlightedSquare>>(None);
+    world.insert_resource(PlayerTurn(PieceColour::White));
+    world.insert_resource(SelectedSquare::default());
+    world.insert_resource(PromotedPawn::default());
+    world.insert_resource(HighlightCastling::default());
+    world.insert_resource(SquareMaterials::placeholder());
+
+    let white_pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (4, 4).into()))
+        .id();
+    let black_pawn = world
+        .spawn()
+        .insert(Piece::black(PieceKind::Pawn, (4, 3).into()))
+        .id();
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::Knight, (5, 5).into()));
+
+    let moves = vec![
+        Move::standard((5, 4).into()),
+        Move::standard((5, 5).into()),
+        Move::en_passant((5, 3).into(), black_pawn),
+    ];
+    world
+        .entity_mut(white_pawn)
+        .insert(ShowMoves(moves.iter().map(|m| m.target_square).collect()));
+    let mut all_valid_moves = AllValidMoves::default();
+    all_valid_moves.insert(white_pawn, moves);
+    world.insert_resource(all_valid_moves);
+    world.insert_resource(SelectedPiece(Some(white_pawn)));
+
+    let squares = [(5, 3), (5, 4), (5, 5)].map(|square| {
+        world
+            .spawn()
+            .insert(Square::from(square))
+            .insert(Handle::<StandardMaterial>::default())
+            .id()
+    });
+
+    let mut update_stage = SystemStage::parallel();
+    update_stage.add_system(colour_squares.system());
+    update_stage.run(&mut world);
+
+    let materials = world.get_resource::<SquareMaterials>().unwrap();
+    let [en_passant, quiet, capture] =
+        squares.map(|square| world.get::<Handle<StandardMaterial>>(square).unwrap());
+    assert_eq!(en_passant, &materials.capture);
+    assert_eq!(quiet, &materials.valid_selection);
+    assert_eq!(capture, &materials.capture);
+}
+
+#[test]
+fn the_selected_piece_should_glow_until_it_is_deselected() {
+    let mut world = World::new();
+    let materials = PieceMaterials::placeholder();
+    let white = materials.get(PieceColour::White);
+    let white_glow = materials.selected(PieceColour::White);
+    world.insert_resource(materials);
+    world.insert_resource(SelectedPiece::default());
+
+    let knight_mesh = world.spawn().insert(white.clone()).id();
+    let knight = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Knight, (0, 1).into()))
+        .insert(Children::with(&[knight_mesh]))
+        .id();
+    let pawn_mesh = world.spawn().insert(white.clone()).id();
+    let pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (1, 1).into()))
+        .insert(Children::with(&[pawn_mesh]))
+        .id();
+
+    let mut update_stage = SystemStage::parallel();
+    update_stage.add_system(glow_selected_piece.system());
+    update_stage.run(&mut world);
+    assert!(world.get::<SelectionGlow>(knight).is_none());
+
+    world.insert_resource(SelectedPiece(Some(knight)));
+    update_stage.run(&mut world);
+    assert!(world.get::<SelectionGlow>(knight).is_some());
+    assert_eq!(
+        world.get::<Handle<StandardMaterial>>(knight_mesh),
+        Some(&white_glow)
+    );
+    assert!(world.get::<SelectionGlow>(pawn).is_none());
+    assert_eq!(
+        world.get::<Handle<StandardMaterial>>(pawn_mesh),
+        Some(&white)
+    );
+
+    world.insert_resource(SelectedPiece(None));
+    update_stage.run(&mut world);
+    assert!(world.get::<SelectionGlow>(knight).is_none());
+    assert_eq!(
+        world.get::<Handle<StandardMaterial>>(knight_mesh),
+        Some(&white)
+    );
+}
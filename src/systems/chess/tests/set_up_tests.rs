@@ -0,0 +1,51 @@
+use crate::model::{BoardScale, Square};
+use crate::systems::chess::game_set_up::border_segments;
+use bevy::prelude::*;
+
+/// the smallest and largest corners of a box with the given centre and size
+fn extents((centre, size): (Vec3, Vec3)) -> (Vec3, Vec3) {
+    (centre - size / 2.0, centre + size / 2.0)
+}
+
+#[test]
+fn the_border_should_surround_the_board_without_overlapping_any_squares() {
+    [BoardScale::default(), BoardScale(2.5)]
+        .into_iter()
+        .for_each(|scale| {
+            let segments = border_segments(scale);
+            let (min, max) = segments.into_iter().map(extents).fold(
+                (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+                |(min, max), (segment_min, segment_max)| {
+                    (min.min(segment_min), max.max(segment_max))
+                },
+            );
+
+            let half_board = 4.0 * scale.0;
+            let half_frame = half_board + 0.5 * scale.0;
+            assert!((min.x + half_frame).abs() < 1e-4, "{:?}", min);
+            assert!((min.z + half_frame).abs() < 1e-4, "{:?}", min);
+            assert!((max.x - half_frame).abs() < 1e-4, "{:?}", max);
+            assert!((max.z - half_frame).abs() < 1e-4, "{:?}", max);
+            assert!(max.y.abs() < 1e-4, "{:?}", max);
+
+            // every square's centre (and so the square itself) is inside the frame's inner edge
+            Square::all().for_each(|square| {
+                let centre = square.to_translation(scale);
+                let (square_min, square_max) = (
+                    centre - Vec3::splat(scale.0 / 2.0),
+                    centre + Vec3::splat(scale.0 / 2.0),
+                );
+
+                segments
+                    .into_iter()
+                    .map(extents)
+                    .for_each(|(segment_min, segment_max)| {
+                        let overlaps = square_min.x < segment_max.x - 1e-4
+                            && square_max.x > segment_min.x + 1e-4
+                            && square_min.z < segment_max.z - 1e-4
+                            && square_max.z > segment_min.z + 1e-4;
+                        assert!(!overlaps, "{:?} overlaps the border", square);
+                    });
+            });
+        });
+}
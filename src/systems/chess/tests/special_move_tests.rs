@@ -1,8 +1,16 @@
-use crate::model::{AllValidMoves, CastlingData, LastPawnDoubleStep, Move, Piece, PieceColour, PieceKind, SpecialMoveData, Square};
+use crate::model::{overlapping_squares, AllValidMoves, BoardScale, CastlingData, CheckState, LastMove, LastPawnDoubleStep, Move, Piece, PieceColour, PieceKind, SpecialMoveData, Square};
+use crate::notation;
+use crate::random::{random_move, SeededRng};
+use crate::systems::chess::game_set_up::{PieceMaterials, PieceMeshes};
+use crate::systems::chess::scripted_moves::ScriptedMoves;
 use crate::systems::chess::{
-    calculate_all_moves, apply_piece_move, GameState, MovePiece, PlayerTurn, PromotedPawn, SelectedPiece,
-    SelectedSquare, Taken,
+    calculate_all_moves, apply_piece_move, log_game, mark_check_in_move_log,
+    promote_pawn_at_final_rank, update_board,
+    AnimationSettings, Board, GameLog, GameLogVerbosity, GameState, MatingMove, MoveLog,
+    MoveNumber, MovePiece, PerformanceStats, PieceCaptured, PlayerTurn, PromotedPawn,
+    PromotionMode, PromotionSettings, SelectedPiece, SelectedSquare, Taken,
 };
+use bevy::app::Events;
 use bevy::ecs::system::Resource;
 use bevy::prelude::*;
 
@@ -10,6 +18,7 @@ trait WorldTestUtils {
     fn overwrite_resource<T: Resource>(&mut self, resource: T);
     fn check_and_overwrite_state(&mut self, expected_state: GameState, new_state: GameState);
     fn move_piece(&mut self, piece_id: Entity, square: Square);
+    fn select_target(&mut self, piece_id: Entity, square: Square);
 }
 
 impl WorldTestUtils for World {
@@ -42,12 +51,17 @@ impl WorldTestUtils for World {
             piece.colour, turn.0
         );
 
+        self.select_target(piece_id, square);
+    }
+
+    /// selects the piece and target square as if they'd been clicked, without checking the move is valid
+    fn select_target(&mut self, piece_id: Entity, square: Square) {
         self.check_and_overwrite_state(GameState::NothingSelected, GameState::TargetSquareSelected);
         self.overwrite_resource(SelectedPiece(Some(piece_id)));
         let square = self
-            .query::<(Entity, &Square)>()
-            .iter(self)
-            .find_map(|(entity, s)| (square == *s).then(|| entity))
+            .get_resource::<Board>()
+            .unwrap()
+            .square_entity(square)
             .unwrap();
         self.overwrite_resource(SelectedSquare(Some(square)));
     }
@@ -57,23 +71,34 @@ fn setup() -> (World, SystemStage) {
     let mut world = World::new();
 
     world.insert_resource(AllValidMoves::default());
+    world.insert_resource(CheckState::default());
+    world.insert_resource::<Option<MatingMove>>(None);
+    world.insert_resource(PerformanceStats::default());
     world.insert_resource(PlayerTurn(PieceColour::Black));
     world.insert_resource(State::new(GameState::NothingSelected));
     world.insert_resource(SelectedSquare::default());
     world.insert_resource(SelectedPiece::default());
     world.insert_resource(PromotedPawn::default());
+    world.insert_resource(MoveNumber::default());
+    world.insert_resource(GameLog::default());
+    world.insert_resource(MoveLog::default());
+    world.insert_resource(ScriptedMoves::default());
+    world.insert_resource(Board::default());
+    world.insert_resource(Events::<PieceCaptured>::default());
+    world.insert_resource(BoardScale::default());
     world.insert_resource(SpecialMoveData::default());
 
-    (0..8).for_each(|x| {
-        (0..8).for_each(|y| {
-            world.spawn().insert(Square { rank: x, file: y });
-        })
+    Square::all().for_each(|square| {
+        world.spawn().insert(square);
     });
 
     let mut update_stage = SystemStage::parallel();
     update_stage.add_system_set(State::<GameState>::get_driver());
+    update_stage.add_system(update_board.system());
     update_stage.add_system_set(
-        SystemSet::on_update(GameState::NothingSelected).with_system(calculate_all_moves.system()),
+        SystemSet::on_update(GameState::NothingSelected)
+            .with_system(calculate_all_moves.system().label("calculate_moves"))
+            .with_system(mark_check_in_move_log.system().after("calculate_moves")),
     );
     update_stage.add_system_set(
         SystemSet::on_update(GameState::TargetSquareSelected).with_system(apply_piece_move.system()),
@@ -182,6 +207,225 @@ fn when_a_pawn_makes_a_two_step_move_an_adjacent_pawn_can_take_it_en_passant_on_
     assert!(world.get::<Taken>(black_pawn).is_some())
 }
 
+#[test]
+fn the_board_should_track_which_piece_is_on_each_square_after_a_capture() {
+    let (mut world, mut stage) = setup();
+
+    world.spawn().insert(Piece::black(PieceKind::King, (7, 4).into()));
+    world.spawn().insert(Piece::white(PieceKind::King, (0, 4).into()));
+    let black_knight = world
+        .spawn()
+        .insert(Piece::black(PieceKind::Knight, (7, 1).into()))
+        .id();
+    let white_pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (5, 2).into()))
+        .id();
+
+    let mut special_moves = world.get_resource_mut::<SpecialMoveData>().unwrap();
+    special_moves.black_castling_data.king_moved = true;
+    special_moves.white_castling_data.king_moved = true;
+
+    stage.run(&mut world);
+
+    let board = world.get_resource::<Board>().unwrap();
+    assert_eq!(board.piece_at((7, 1).into()), Some(black_knight));
+    assert_eq!(board.piece_at((5, 2).into()), Some(white_pawn));
+    assert!(board.square_entity((5, 2).into()).is_some());
+
+    world.move_piece(black_knight, (5, 2).into());
+    stage.run(&mut world);
+    stage.run(&mut world);
+    // the board is only updated once the move has finished, so it lags behind by a frame
+    stage.run(&mut world);
+
+    let board = world.get_resource::<Board>().unwrap();
+    assert_eq!(board.piece_at((5, 2).into()), Some(black_knight));
+    assert_eq!(board.piece_at((7, 1).into()), None);
+    assert_ne!(board.piece_at((5, 2).into()), Some(white_pawn));
+}
+
+#[test]
+fn an_en_passant_capture_should_announce_the_pawn_beside_the_destination() {
+    let (mut world, mut stage) = setup();
+
+    world.spawn().insert(Piece::black(PieceKind::King, (7, 4).into()));
+    world.spawn().insert(Piece::white(PieceKind::King, (0, 4).into()));
+    let white_pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (4, 3).into()))
+        .id();
+    let black_pawn = world
+        .spawn()
+        .insert(Piece::black(PieceKind::Pawn, (4, 4).into()))
+        .id();
+
+    let mut special_moves = world.get_resource_mut::<SpecialMoveData>().unwrap();
+    special_moves.black_castling_data.king_moved = true;
+    special_moves.white_castling_data.king_moved = true;
+    special_moves.last_pawn_double_step = Some(LastPawnDoubleStep {
+        pawn_id: black_pawn,
+        square: (4, 4).into(),
+    });
+    world.overwrite_resource(PlayerTurn(PieceColour::White));
+
+    stage.run(&mut world);
+
+    world.move_piece(white_pawn, (5, 4).into());
+    stage.run(&mut world);
+
+    let events = world.get_resource::<Events<PieceCaptured>>().unwrap();
+    let captures = events.get_reader().iter(events).collect::<Vec<_>>();
+    assert_eq!(
+        captures,
+        vec![&PieceCaptured {
+            by: PieceColour::White,
+            kind: PieceKind::Pawn,
+            square: (4, 4).into(),
+            en_passant: true,
+        }]
+    );
+    assert_eq!(captures[0].message(), "White captures Pawn en passant");
+}
+
+#[test]
+fn a_pawn_capturing_onto_the_final_rank_should_take_the_piece_and_then_be_promoted() {
+    let (mut world, mut stage) = setup();
+
+    world.spawn().insert(Piece::black(PieceKind::King, (7, 4).into()));
+    world.spawn().insert(Piece::white(PieceKind::King, (0, 4).into()));
+    let black_rook = world
+        .spawn()
+        .insert(Piece::black(PieceKind::Rook, (7, 0).into()))
+        .id();
+    let white_pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (6, 1).into()))
+        .id();
+
+    let mut special_moves = world.get_resource_mut::<SpecialMoveData>().unwrap();
+    special_moves.white_castling_data.king_moved = true;
+    world.overwrite_resource(PlayerTurn(PieceColour::White));
+
+    stage.run(&mut world);
+
+    world.move_piece(white_pawn, (7, 0).into());
+    stage.run(&mut world);
+
+    assert!(world.get::<Taken>(black_rook).is_some());
+    assert_eq!(
+        world.get_resource::<PromotedPawn>().unwrap().0,
+        Some(white_pawn)
+    );
+    // taking the Rook on its starting square means Black can't castle with it any more
+    assert!(
+        world
+            .get_resource::<SpecialMoveData>()
+            .unwrap()
+            .black_castling_data
+            .queenside_rook_moved
+    );
+
+    let events = world.get_resource::<Events<PieceCaptured>>().unwrap();
+    let captures = events.get_reader().iter(events).collect::<Vec<_>>();
+    assert_eq!(
+        captures,
+        vec![&PieceCaptured {
+            by: PieceColour::White,
+            kind: PieceKind::Rook,
+            square: (7, 0).into(),
+            en_passant: false,
+        }]
+    );
+
+    stage.run(&mut world);
+    assert_eq!(world.get::<Piece>(white_pawn).unwrap().square, (7, 0).into());
+
+    world.insert_resource(Input::<KeyCode>::default());
+    world.insert_resource(AnimationSettings { instant: true });
+    world.insert_resource(PromotionSettings {
+        white: PromotionMode::AlwaysQueen,
+        black: PromotionMode::Prompt,
+    });
+    world.insert_resource(PieceMeshes::placeholder());
+    world.insert_resource(PieceMaterials {
+        white: Handle::default(),
+        black: Handle::default(),
+        white_preview: Handle::default(),
+        black_preview: Handle::default(),
+        white_selected: Handle::default(),
+        black_selected: Handle::default(),
+    });
+    world.check_and_overwrite_state(GameState::NothingSelected, GameState::PawnPromotion);
+
+    let mut promotion_stage = SystemStage::parallel();
+    promotion_stage.add_system_set(State::<GameState>::get_driver());
+    promotion_stage.add_system_set(
+        SystemSet::on_update(GameState::PawnPromotion)
+            .with_system(promote_pawn_at_final_rank.system()),
+    );
+    promotion_stage.run(&mut world);
+
+    let pieces_on_a8 = world
+        .query::<&Piece>()
+        .iter(&world)
+        .filter(|piece| piece.square == (7, 0).into())
+        .copied()
+        .collect::<Vec<_>>();
+    assert_eq!(
+        pieces_on_a8,
+        vec![Piece::white(PieceKind::Queen, (7, 0).into())]
+    );
+}
+
+#[test]
+fn when_a_pawn_double_steps_between_two_enemy_pawns_both_of_them_can_take_it_en_passant() {
+    let (mut world, mut stage) = setup();
+
+    world.spawn().insert(Piece::black(PieceKind::King, (7, 4).into()));
+    world.spawn().insert(Piece::white(PieceKind::King, (0, 4).into()));
+
+    let black_pawn = world
+        .spawn()
+        .insert(Piece::black(PieceKind::Pawn, (6, 4).into()))
+        .id();
+    // the right-hand pawn is spawned first, so it's found before the left-hand one
+    let right_pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (4, 5).into()))
+        .id();
+    let left_pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (4, 3).into()))
+        .id();
+
+    let mut special_moves = world.get_resource_mut::<SpecialMoveData>().unwrap();
+    special_moves.black_castling_data.king_moved = true;
+    special_moves.white_castling_data.king_moved = true;
+
+    stage.run(&mut world);
+
+    world.move_piece(black_pawn, (4, 4).into());
+    stage.run(&mut world);
+    stage.run(&mut world);
+
+    let all_valid_moves = world.get_resource::<AllValidMoves>().unwrap();
+    [left_pawn, right_pawn].into_iter().for_each(|pawn| {
+        assert!(
+            all_valid_moves
+                .get(pawn)
+                .contains(&Move::en_passant((5, 4).into(), black_pawn)),
+            "{:?}",
+            all_valid_moves.get(pawn)
+        );
+    });
+
+    world.move_piece(right_pawn, (5, 4).into());
+    stage.run(&mut world);
+
+    assert!(world.get::<Taken>(black_pawn).is_some());
+}
+
 #[test]
 fn when_a_pawn_makes_a_two_step_move_an_adjacent_pawn_cannot_take_it_en_passant_if_a_turn_has_passed(
 ) {
@@ -266,6 +510,61 @@ fn when_a_pawn_makes_a_two_step_move_an_adjacent_pawn_cannot_take_it_en_passant_
     );
 }
 
+#[test]
+fn a_stale_en_passant_move_should_be_rejected_once_the_double_step_is_no_longer_the_last_move() {
+    let (mut world, mut stage) = setup();
+
+    let black_king = world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, (7, 4).into()))
+        .id();
+    let white_king = world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, (0, 4).into()))
+        .id();
+    let black_pawn = world
+        .spawn()
+        .insert(Piece::black(PieceKind::Pawn, (6, 4).into()))
+        .id();
+    let white_pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (4, 3).into()))
+        .id();
+
+    let mut special_moves = world.get_resource_mut::<SpecialMoveData>().unwrap();
+    special_moves.black_castling_data.king_moved = true;
+    special_moves.white_castling_data.king_moved = true;
+
+    stage.run(&mut world);
+    world.move_piece(black_pawn, (4, 4).into());
+    stage.run(&mut world);
+    stage.run(&mut world);
+    world.move_piece(white_king, (1, 4).into());
+    stage.run(&mut world);
+    world.move_piece(black_king, (6, 4).into());
+    stage.run(&mut world);
+
+    // e.g. the moves shown on screen weren't updated after the window closed
+    world
+        .get_resource_mut::<AllValidMoves>()
+        .unwrap()
+        .insert(white_pawn, vec![Move::en_passant((5, 4).into(), black_pawn)]);
+    world.select_target(white_pawn, (5, 4).into());
+    stage.run(&mut world);
+
+    assert!(world.get::<Taken>(black_pawn).is_none());
+    assert_eq!(world.get::<Piece>(black_pawn).unwrap().square, (4, 4).into());
+    assert_eq!(world.get::<Piece>(white_pawn).unwrap().square, (4, 3).into());
+    assert_eq!(
+        world.get_resource::<PlayerTurn>().unwrap().0,
+        PieceColour::White
+    );
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::NothingSelected
+    );
+}
+
 #[test]
 fn it_should_be_possible_to_take_a_pawn_with_the_king_in_check_using_en_passant() {
     let (mut world, mut stage) = setup();
@@ -729,6 +1028,50 @@ fn it_should_be_possible_to_castle_kingside_if_neither_the_king_nor_the_rook_hav
     assert_eq!(white_rook.square.file, 5);
 }
 
+#[test]
+fn it_should_be_possible_to_castle_by_selecting_the_kings_destination_instead_of_the_rook() {
+    let (mut world, mut stage) = setup();
+
+    let white_king = world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, (0, 4).into()))
+        .id();
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, (7, 4).into()));
+    let white_rook = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Rook, (0, 7).into()))
+        .id();
+
+    let mut special_moves = world.get_resource_mut::<SpecialMoveData>().unwrap();
+    special_moves.white_castling_data.queenside_rook_moved = true;
+    special_moves.black_castling_data.king_moved = true;
+
+    world.overwrite_resource(PlayerTurn(PieceColour::White));
+
+    stage.run(&mut world);
+
+    world.select_target(white_king, (0, 6).into());
+    stage.run(&mut world);
+
+    assert_eq!(
+        world.get::<Piece>(white_king).unwrap().square,
+        Square::new(0, 6)
+    );
+    assert_eq!(
+        world.get::<Piece>(white_rook).unwrap().square,
+        Square::new(0, 5)
+    );
+    assert!(
+        world
+            .get_resource::<SpecialMoveData>()
+            .unwrap()
+            .white_castling_data
+            .kingside_rook_moved
+    );
+}
+
 #[test]
 fn it_should_not_be_possible_to_castle_if_the_king_has_moved() {
     let (mut world, mut stage) = setup();
@@ -1098,6 +1441,109 @@ fn it_should_not_be_possible_to_castle_if_any_square_the_king_would_move_through
     );
 }
 
+#[test]
+fn it_should_be_possible_to_castle_if_the_rook_is_attacked() {
+    let (mut world, mut stage) = setup();
+
+    let white_king = world
+        .spawn()
+        .insert(Piece {
+            kind: PieceKind::King,
+            colour: PieceColour::White,
+            square: (0, 4).into(),
+        })
+        .id();
+
+    world.spawn().insert(Piece {
+        kind: PieceKind::King,
+        colour: PieceColour::Black,
+        square: (7, 4).into(),
+    });
+
+    let white_rook = world
+        .spawn()
+        .insert(Piece {
+            kind: PieceKind::Rook,
+            colour: PieceColour::White,
+            square: (0, 7).into(),
+        })
+        .id();
+
+    // attacks the white rook, but none of the squares the king moves through
+    world.spawn().insert(Piece {
+        kind: PieceKind::Rook,
+        colour: PieceColour::Black,
+        square: (7, 7).into(),
+    });
+
+    let mut special_moves = world.get_resource_mut::<SpecialMoveData>().unwrap();
+    special_moves.white_castling_data.queenside_rook_moved = true;
+    special_moves.black_castling_data.king_moved = true;
+
+    world.overwrite_resource(PlayerTurn(PieceColour::White));
+
+    stage.run(&mut world);
+
+    world.move_piece(white_king, (0, 7).into());
+    stage.run(&mut world);
+
+    let white_king = world.get::<Piece>(white_king).unwrap();
+    assert_eq!(white_king.square, Square::new(0, 6));
+
+    let white_rook = world.get::<Piece>(white_rook).unwrap();
+    assert_eq!(white_rook.square, Square::new(0, 5));
+}
+
+#[test]
+fn it_should_be_possible_to_castle_queenside_if_only_the_rook_passes_through_an_attacked_square() {
+    let (mut world, mut stage) = setup();
+
+    let black_king = world
+        .spawn()
+        .insert(Piece {
+            kind: PieceKind::King,
+            colour: PieceColour::Black,
+            square: (7, 4).into(),
+        })
+        .id();
+
+    world.spawn().insert(Piece {
+        kind: PieceKind::King,
+        colour: PieceColour::White,
+        square: (0, 4).into(),
+    });
+
+    let black_rook = world
+        .spawn()
+        .insert(Piece {
+            kind: PieceKind::Rook,
+            colour: PieceColour::Black,
+            square: (7, 0).into(),
+        })
+        .id();
+
+    // attacks the square between the rook and the king's target square
+    world.spawn().insert(Piece {
+        kind: PieceKind::Rook,
+        colour: PieceColour::White,
+        square: (0, 1).into(),
+    });
+
+    let mut special_moves = world.get_resource_mut::<SpecialMoveData>().unwrap();
+    special_moves.black_castling_data.kingside_rook_moved = true;
+
+    stage.run(&mut world);
+
+    world.move_piece(black_king, (7, 0).into());
+    stage.run(&mut world);
+
+    let black_king = world.get::<Piece>(black_king).unwrap();
+    assert_eq!(black_king.square, Square::new(7, 2));
+
+    let black_rook = world.get::<Piece>(black_rook).unwrap();
+    assert_eq!(black_rook.square, Square::new(7, 3));
+}
+
 #[test]
 fn it_should_not_be_possible_to_castle_if_the_rook_has_been_taken() {
     let (mut world, mut stage) = setup();
@@ -1159,3 +1605,395 @@ fn it_should_not_be_possible_to_castle_if_the_rook_has_been_taken() {
         ]
     );
 }
+
+#[test]
+fn it_should_not_be_possible_to_move_a_piece_after_checkmate() {
+    let (mut world, mut stage) = setup();
+
+    let black_king = world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, (7, 7).into()))
+        .id();
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, (0, 4).into()));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::Rook, (7, 0).into()));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::Rook, (6, 0).into()));
+
+    stage.run(&mut world);
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::Checkmate(PieceColour::Black)
+    );
+
+    let target_square = world
+        .query::<(Entity, &Square)>()
+        .iter(&world)
+        .find_map(|(entity, square)| (*square == Square::new(6, 6)).then(|| entity))
+        .unwrap();
+    world.overwrite_resource(SelectedPiece(Some(black_king)));
+    world.overwrite_resource(SelectedSquare(Some(target_square)));
+
+    stage.run(&mut world);
+    stage.run(&mut world);
+
+    assert_eq!(
+        world.get::<Piece>(black_king).unwrap().square,
+        Square::new(7, 7)
+    );
+    assert!(world.get::<MovePiece>(black_king).is_none());
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::Checkmate(PieceColour::Black)
+    );
+}
+
+#[test]
+fn checkmate_should_record_the_mating_move_and_the_piece_giving_check() {
+    let (mut world, mut stage) = setup();
+    world.overwrite_resource(PlayerTurn(PieceColour::White));
+
+    let mut special_moves = world.get_resource_mut::<SpecialMoveData>().unwrap();
+    special_moves.black_castling_data.king_moved = true;
+    special_moves.white_castling_data.king_moved = true;
+
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, (7, 7).into()));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, (5, 5).into()));
+    let queen = Piece::white(PieceKind::Queen, (1, 6).into());
+    let queen_id = world.spawn().insert(queen).id();
+
+    stage.run(&mut world);
+    assert!(world.get_resource::<Option<MatingMove>>().unwrap().is_none());
+
+    world.move_piece(queen_id, (6, 6).into());
+    stage.run(&mut world);
+    stage.run(&mut world);
+
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::Checkmate(PieceColour::Black)
+    );
+    assert_eq!(
+        world.get_resource::<Option<MatingMove>>().unwrap(),
+        &Some(MatingMove {
+            last_move: Some(LastMove {
+                piece_id: queen_id,
+                piece: queen,
+                target_square: (6, 6).into(),
+            }),
+            checking_pieces: vec![Piece::white(PieceKind::Queen, (6, 6).into())],
+        })
+    );
+}
+
+#[test]
+fn a_second_click_while_a_piece_is_moving_should_not_commit_another_move() {
+    let (mut world, _) = setup();
+
+    // no fake movement, so the first move stays in progress
+    let mut stage = SystemStage::parallel();
+    stage.add_system_set(State::<GameState>::get_driver());
+    stage.add_system_set(
+        SystemSet::on_update(GameState::NothingSelected).with_system(calculate_all_moves.system()),
+    );
+    stage.add_system_set(
+        SystemSet::on_update(GameState::TargetSquareSelected).with_system(apply_piece_move.system()),
+    );
+
+    let mut special_moves = world.get_resource_mut::<SpecialMoveData>().unwrap();
+    special_moves.black_castling_data.king_moved = true;
+    special_moves.white_castling_data.king_moved = true;
+
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, (7, 4).into()));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, (0, 4).into()));
+    let rook = world
+        .spawn()
+        .insert(Piece::black(PieceKind::Rook, (7, 0).into()))
+        .id();
+    let knight = world
+        .spawn()
+        .insert(Piece::black(PieceKind::Knight, (7, 6).into()))
+        .id();
+
+    stage.run(&mut world);
+
+    world.move_piece(rook, (5, 0).into());
+    stage.run(&mut world);
+    assert_eq!(
+        world.get::<MovePiece>(rook).unwrap().target_square(),
+        (5, 0).into()
+    );
+
+    world.check_and_overwrite_state(GameState::MovingPiece, GameState::TargetSquareSelected);
+    world.overwrite_resource(SelectedPiece(Some(knight)));
+    let knight_target = world
+        .query::<(Entity, &Square)>()
+        .iter(&world)
+        .find_map(|(entity, square)| (*square == Square::new(5, 5)).then(|| entity))
+        .unwrap();
+    world.overwrite_resource(SelectedSquare(Some(knight_target)));
+    stage.run(&mut world);
+
+    assert!(world.get::<MovePiece>(knight).is_none());
+    assert_eq!(
+        world.get::<MovePiece>(rook).unwrap().target_square(),
+        (5, 0).into()
+    );
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::MovingPiece
+    );
+}
+
+#[test]
+fn taking_the_last_piece_other_than_the_kings_should_be_a_draw() {
+    let (mut world, mut stage) = setup();
+
+    let mut special_moves = world.get_resource_mut::<SpecialMoveData>().unwrap();
+    special_moves.black_castling_data.king_moved = true;
+    special_moves.white_castling_data.king_moved = true;
+
+    let black_king = world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, (7, 4).into()))
+        .id();
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, (0, 0).into()));
+    let white_pawn = world
+        .spawn()
+        .insert(Piece::white(PieceKind::Pawn, (6, 4).into()))
+        .id();
+
+    stage.run(&mut world);
+
+    world.move_piece(black_king, (6, 4).into());
+    stage.run(&mut world);
+    stage.run(&mut world);
+
+    assert!(world.get::<Taken>(white_pawn).is_some());
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::Draw
+    );
+}
+
+#[test]
+fn a_move_made_stale_by_the_piece_being_taken_should_be_rejected() {
+    let (mut world, mut stage) = setup();
+
+    let mut special_moves = world.get_resource_mut::<SpecialMoveData>().unwrap();
+    special_moves.black_castling_data.king_moved = true;
+    special_moves.white_castling_data.king_moved = true;
+
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::King, (7, 4).into()));
+    world
+        .spawn()
+        .insert(Piece::black(PieceKind::Knight, (7, 6).into()));
+    world
+        .spawn()
+        .insert(Piece::white(PieceKind::King, (0, 4).into()));
+    let rook = world
+        .spawn()
+        .insert(Piece::black(PieceKind::Rook, (7, 0).into()))
+        .id();
+
+    stage.run(&mut world);
+
+    world.move_piece(rook, (5, 0).into());
+    // the rook's moves are still in `AllValidMoves`, but it's no longer on the board
+    world.entity_mut(rook).remove::<Piece>();
+    stage.run(&mut world);
+
+    assert!(world.get::<MovePiece>(rook).is_none());
+    assert!(world.get_resource::<SpecialMoveData>().unwrap().last_move.is_none());
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::NothingSelected
+    );
+}
+
+fn assert_board_is_consistent(world: &mut World, seed: u64, ply: usize) {
+    let pieces = world
+        .query::<&Piece>()
+        .iter(world)
+        .copied()
+        .collect::<Vec<_>>();
+
+    PieceColour::all().into_iter().for_each(|colour| {
+        let kings = pieces
+            .iter()
+            .filter(|piece| piece.kind == PieceKind::King && piece.colour == colour)
+            .count();
+        assert_eq!(
+            kings, 1,
+            "seed {}, ply {}: {} has {} Kings",
+            seed, ply, colour, kings
+        );
+    });
+
+    pieces.iter().for_each(|piece| {
+        assert!(
+            piece.square.rank < 8 && piece.square.file < 8,
+            "seed {}, ply {}: {:?} is off the board",
+            seed,
+            ply,
+            piece
+        );
+    });
+
+    assert_eq!(
+        overlapping_squares(&pieces),
+        vec![],
+        "seed {}, ply {}: more than one piece on the same square",
+        seed,
+        ply
+    );
+}
+
+#[test]
+fn random_legal_games_should_keep_the_board_consistent() {
+    const GAMES: u64 = 10;
+    const MAX_PLIES: usize = 150;
+
+    (1..=GAMES).for_each(|seed| {
+        let (mut world, mut stage) = setup();
+        let mut rng = SeededRng::new(seed);
+
+        world.overwrite_resource(PlayerTurn(PieceColour::White));
+        Piece::starting_position().into_iter().for_each(|piece| {
+            world.spawn().insert(piece);
+        });
+
+        stage.run(&mut world);
+
+        for ply in 0..MAX_PLIES {
+            assert_board_is_consistent(&mut world, seed, ply);
+
+            let state = world
+                .get_resource::<State<GameState>>()
+                .unwrap()
+                .current()
+                .clone();
+            if state.is_game_over() {
+                break;
+            }
+            assert_eq!(state, GameState::NothingSelected, "seed {}, ply {}", seed, ply);
+
+            let all_valid_moves = world.get_resource::<AllValidMoves>().unwrap();
+            let (piece_id, target) = random_move(all_valid_moves, &mut rng).unwrap_or_else(|| {
+                panic!("seed {}, ply {}: no moves but the game isn't over", seed, ply)
+            });
+            world.move_piece(piece_id, target);
+            stage.run(&mut world);
+            stage.run(&mut world);
+        }
+    });
+}
+
+#[test]
+fn the_game_log_should_record_each_move_and_the_result() {
+    let (mut world, mut stage) = setup();
+    stage.add_system(log_game.system());
+
+    world.overwrite_resource(PlayerTurn(PieceColour::White));
+    world.get_resource_mut::<GameLog>().unwrap().verbosity = GameLogVerbosity::MovesAndPositions;
+    let pieces = Piece::starting_position()
+        .into_iter()
+        .map(|piece| (piece.square, world.spawn().insert(piece).id()))
+        .collect::<Vec<_>>();
+    let piece_on = |square: Square| {
+        pieces
+            .iter()
+            .find_map(|(start, id)| (*start == square).then(|| *id))
+            .unwrap()
+    };
+
+    stage.run(&mut world);
+
+    // Fool's Mate
+    [
+        ((1, 5), (2, 5)),
+        ((6, 4), (4, 4)),
+        ((1, 6), (3, 6)),
+        ((7, 3), (3, 7)),
+    ]
+    .into_iter()
+    .for_each(|(from, to)| {
+        world.move_piece(piece_on(from.into()), to.into());
+        stage.run(&mut world);
+        stage.run(&mut world);
+    });
+    stage.run(&mut world);
+
+    assert_eq!(
+        world.get_resource::<GameLog>().unwrap().lines,
+        vec![
+            "1. f2f3 f3 rnbqkbnr/pppppppp/8/8/8/5P2/PPPPP1PP/RNBQKBNR b KQkq - 0 1",
+            "1... e7e5 e5 rnbqkbnr/pppp1ppp/8/4p3/8/5P2/PPPPP1PP/RNBQKBNR w KQkq e6 0 2",
+            "2. g2g4 g4 rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2",
+            "2... d8h4 Qh4 rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 0 3",
+            "0-1 Checkmate(White)",
+        ]
+    );
+    assert_eq!(
+        world.get_resource::<MoveLog>().unwrap().0,
+        vec!["f3", "e5", "g4", "Qh4#"]
+    );
+    assert!(
+        notation::to_pgn(&world.get_resource::<MoveLog>().unwrap().0, Some("0-1"))
+            .ends_with("1. f3 e5 2. g4 Qh4# 0-1\n")
+    );
+}
+
+#[test]
+fn a_move_that_gives_check_should_be_marked_in_the_move_log() {
+    let (mut world, mut stage) = setup();
+
+    world.overwrite_resource(PlayerTurn(PieceColour::White));
+    let pieces = Piece::starting_position()
+        .into_iter()
+        .map(|piece| (piece.square, world.spawn().insert(piece).id()))
+        .collect::<Vec<_>>();
+    let piece_on = |square: Square| {
+        pieces
+            .iter()
+            .find_map(|(start, id)| (*start == square).then(|| *id))
+            .unwrap()
+    };
+
+    stage.run(&mut world);
+
+    [((1, 4), (3, 4)), ((6, 5), (5, 5)), ((0, 3), (4, 7))]
+        .into_iter()
+        .for_each(|(from, to)| {
+            world.move_piece(piece_on(from.into()), to.into());
+            stage.run(&mut world);
+            stage.run(&mut world);
+        });
+    // recalculating the same position shouldn't mark it again
+    stage.run(&mut world);
+
+    assert_eq!(
+        world.get_resource::<MoveLog>().unwrap().0,
+        vec!["e4", "f6", "Qh5+"]
+    );
+    assert_eq!(
+        world.get_resource::<State<GameState>>().unwrap().current(),
+        &GameState::NothingSelected
+    );
+}
@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+
+pub struct LightingPlugin;
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LightingSettings>()
+            .add_startup_system(spawn_lights);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LightSettings {
+    pub position: Vec3,
+    pub intensity: f32,
+}
+
+/// read once on start up; insert this before adding the plugin to light a custom theme differently
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightingSettings {
+    pub main: LightSettings,
+    /// a second, usually dimmer, light to fill in the shadows cast by the main light
+    pub fill: Option<LightSettings>,
+}
+
+impl Default for LightingSettings {
+    fn default() -> Self {
+        Self {
+            main: LightSettings {
+                position: Vec3::new(1.0, 10.0, 2.0),
+                intensity: 2000.0,
+            },
+            fill: None,
+        }
+    }
+}
+
+fn spawn_lights(mut commands: Commands, settings: Res<LightingSettings>) {
+    [Some(settings.main), settings.fill]
+        .into_iter()
+        .flatten()
+        .for_each(|light| {
+            commands.spawn_bundle(PointLightBundle {
+                transform: Transform::from_translation(light.position),
+                point_light: PointLight {
+                    intensity: light.intensity,
+                    range: 60.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawned_lights(settings: LightingSettings) -> Vec<(Vec3, f32)> {
+        let mut world = World::new();
+        world.insert_resource(settings);
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(spawn_lights.system());
+        stage.run(&mut world);
+
+        world
+            .query::<(&Transform, &PointLight)>()
+            .iter(&world)
+            .map(|(transform, light)| (transform.translation, light.intensity))
+            .collect()
+    }
+
+    #[test]
+    fn the_light_should_be_spawned_at_the_configured_position() {
+        let lights = spawned_lights(LightingSettings {
+            main: LightSettings {
+                position: Vec3::new(-3.0, 12.0, 4.0),
+                intensity: 1500.0,
+            },
+            fill: None,
+        });
+
+        assert_eq!(lights, vec![(Vec3::new(-3.0, 12.0, 4.0), 1500.0)]);
+    }
+
+    #[test]
+    fn a_fill_light_should_be_spawned_if_configured() {
+        let mut lights = spawned_lights(LightingSettings {
+            fill: Some(LightSettings {
+                position: Vec3::new(0.0, 8.0, -6.0),
+                intensity: 500.0,
+            }),
+            ..Default::default()
+        });
+        lights.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+
+        assert_eq!(
+            lights,
+            vec![
+                (Vec3::new(0.0, 8.0, -6.0), 500.0),
+                (Vec3::new(1.0, 10.0, 2.0), 2000.0),
+            ]
+        );
+    }
+}
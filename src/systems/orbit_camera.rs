@@ -1,12 +1,92 @@
 use bevy::app::{EventReader, Plugin};
 use bevy::prelude::*;
-use std::f32::consts::FRAC_PI_2;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, SQRT_2};
 use bevy::input::mouse::MouseMotion;
+use crate::easing;
+use crate::model::{BoardScale, PieceColour, BOARD_SIZE};
 
 pub struct OrbitCameraPlugin;
 impl Plugin for OrbitCameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(rotate_camera);
+        app.init_resource::<ViewingSide>()
+            .init_resource::<CameraSettings>()
+            .add_system(flip_camera.before("rotate_camera"))
+            .add_system(rotate_camera.label("rotate_camera"));
+    }
+}
+
+const FLIP_SECONDS: f32 = 0.8;
+
+/// which player's side of the board the camera is on,
+/// i.e. who's sitting in front of the screen when two players are sharing it
+///
+/// this is only about the view - whoever's sitting there, clicks only ever move the pieces of the
+/// player whose turn it is
+pub struct ViewingSide(pub PieceColour);
+
+impl Default for ViewingSide {
+    fn default() -> Self {
+        ViewingSide(PieceColour::White)
+    }
+}
+
+/// how far the camera needs to be from the centre of the board to fit all of it on screen,
+/// from any angle
+///
+/// a wide window is limited by its vertical field of view, and a tall window by its horizontal one
+pub fn framing_distance(aspect_ratio: f32, vertical_fov: f32, board_scale: BoardScale) -> f32 {
+    let horizontal_fov = 2.0 * ((vertical_fov / 2.0).tan() * aspect_ratio).atan();
+    let fov = vertical_fov.min(horizontal_fov);
+    let board_radius = (BOARD_SIZE as f32 / 2.0) * SQRT_2 * board_scale.0;
+
+    board_radius / (fov / 2.0).sin()
+}
+
+/// how the camera starts out, looking at the board from White's side
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraSettings {
+    pub position: Vec3,
+    pub target: Vec3,
+    /// the vertical field of view, in radians
+    pub fov: f32,
+    /// moves the camera along the line from `target` to `position` until the whole board just fits
+    /// in the window, so `position` only sets the angle the board is viewed from
+    pub fit_to_window: bool,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            position: Vec3::new(0.0, 13.0, -9.0),
+            target: Vec3::ZERO,
+            fov: FRAC_PI_4,
+            fit_to_window: true,
+        }
+    }
+}
+
+impl CameraSettings {
+    /// looking down on the board, with White at the bottom of the screen
+    pub fn top_down() -> Self {
+        CameraSettings {
+            // not quite straight down, or there'd be no way to tell which way is up
+            position: Vec3::new(0.0, 1.0, -0.001),
+            ..Default::default()
+        }
+    }
+
+    pub fn eye(&self, aspect_ratio: f32, board_scale: BoardScale) -> Vec3 {
+        if self.fit_to_window {
+            let distance = framing_distance(aspect_ratio, self.fov, board_scale);
+            self.target + ((self.position - self.target).normalize() * distance)
+        } else {
+            self.position
+        }
+    }
+
+    pub fn transform(&self, aspect_ratio: f32, board_scale: BoardScale) -> Transform {
+        Transform::from_translation(self.eye(aspect_ratio, board_scale))
+            .looking_at(self.target, Vec3::Y)
     }
 }
 
@@ -17,6 +97,10 @@ pub struct GameCamera {
     pitch: f32,
     initial_yaw: f32,
     yaw_offset: f32,
+    flipped: bool,
+    /// the yaw the current flip started from - only meaningful while `flip_progress` < 1
+    flip_from: f32,
+    flip_progress: f32,
 }
 
 impl GameCamera {
@@ -42,8 +126,65 @@ impl GameCamera {
             pitch,
             initial_yaw: yaw,
             yaw_offset: 0.0,
+            flipped: false,
+            flip_from: yaw,
+            flip_progress: 1.0,
         }
     }
+
+    /// starts the camera on the given player's side of the board, without animating a flip
+    /// the camera is assumed to start on White's side
+    pub fn viewed_from(mut self, side: PieceColour) -> Self {
+        self.flipped = side == PieceColour::Black;
+        self.flip_from = self.target_yaw();
+        self
+    }
+
+    /// the yaw the camera is settling towards, ignoring any rotation the user is currently applying
+    pub fn target_yaw(&self) -> f32 {
+        if self.flipped {
+            self.initial_yaw + PI
+        } else {
+            self.initial_yaw
+        }
+    }
+
+    /// rotates the board 180° to look at it from the other player's side
+    /// flipping again mid-flip turns back from wherever the camera currently is
+    pub fn flip(&mut self) {
+        self.flip_from = self.current_yaw();
+        self.flipped = !self.flipped;
+        self.flip_progress = 0.0;
+    }
+
+    fn current_yaw(&self) -> f32 {
+        let progress = (easing::sigmoid(-0.3)((self.flip_progress * 2.0) - 1.0) + 1.0) / 2.0;
+        self.flip_from + ((self.target_yaw() - self.flip_from) * progress)
+    }
+
+    /// where the camera sits when turned to the given yaw, at the same pitch and distance from the target
+    fn eye_at_yaw(&self, yaw: f32) -> Vec3 {
+        let rotated_look_dir = {
+            let ray = Mat3::from_rotation_y(yaw) * Vec3::Z;
+            let pitch_axis = ray.cross(Vec3::Y);
+
+            Mat3::from_axis_angle(pitch_axis, self.pitch) * ray
+        };
+        let look_dir_magnitude = (self.eye - self.target).length();
+        self.target + (rotated_look_dir * look_dir_magnitude)
+    }
+}
+
+/// swaps seats, for two players sharing a screen - this doesn't touch the game itself
+fn flip_camera(
+    mut cameras: Query<&mut GameCamera>,
+    mut viewing_side: ResMut<ViewingSide>,
+    input: Res<Input<KeyCode>>,
+) {
+    if input.just_pressed(KeyCode::F) {
+        cameras.for_each_mut(|mut camera| camera.flip());
+        viewing_side.0 = viewing_side.0.opposite();
+    }
 }
 
 fn rotate_camera(
@@ -72,19 +213,204 @@ fn rotate_camera(
         }
     };
 
-    if yaw_offset.abs() > FRAC_PI_2 {
-        return;
-    }
-
-    let rotated_look_dir = {
-        let ray = Mat3::from_rotation_y(camera.initial_yaw + yaw_offset) * Vec3::Z;
-        let pitch_axis = ray.cross(Vec3::Y);
-
-        Mat3::from_axis_angle(pitch_axis, camera.pitch) * ray
+    // the user can't rotate past the sides of the board, but shouldn't stop a flip from finishing either
+    let yaw_offset = if yaw_offset.abs() > FRAC_PI_2 {
+        camera.yaw_offset
+    } else {
+        yaw_offset
     };
-    let look_dir_magnitude = (camera.eye - camera.target).length();
-    camera.eye = camera.target + (rotated_look_dir * look_dir_magnitude);
+    camera.flip_progress = (camera.flip_progress + (time.delta_seconds() / FLIP_SECONDS)).min(1.0);
+
+    camera.eye = camera.eye_at_yaw(camera.current_yaw() + yaw_offset);
     camera.yaw_offset = yaw_offset;
 
     *transform = Transform::from_translation(camera.eye).looking_at(camera.target, Vec3::Y);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{board_plane_intersection, Piece, Square};
+    use crate::systems::chess::PlayerTurn;
+
+    #[test]
+    fn pressing_f_should_toggle_the_target_yaw_by_180_degrees() {
+        let mut world = World::new();
+        world.insert_resource(Input::<KeyCode>::default());
+        world.insert_resource(ViewingSide::default());
+        let camera = world
+            .spawn()
+            .insert(GameCamera::new(Vec3::new(0.0, 13.0, -9.0), Vec3::ZERO))
+            .id();
+        let initial_yaw = world.get::<GameCamera>(camera).unwrap().target_yaw();
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(flip_camera.system());
+
+        let mut press_f = |world: &mut World| {
+            let mut input = world.get_resource_mut::<Input<KeyCode>>().unwrap();
+            input.clear();
+            input.release(KeyCode::F);
+            input.press(KeyCode::F);
+            stage.run(world);
+        };
+
+        press_f(&mut world);
+        let flipped_yaw = world.get::<GameCamera>(camera).unwrap().target_yaw();
+        assert!((flipped_yaw - initial_yaw - PI).abs() < 0.0001);
+
+        press_f(&mut world);
+        let unflipped_yaw = world.get::<GameCamera>(camera).unwrap().target_yaw();
+        assert!((unflipped_yaw - initial_yaw).abs() < 0.0001);
+    }
+
+    #[test]
+    fn swapping_sides_should_only_change_the_camera_and_who_is_viewing() {
+        let mut world = World::new();
+        let mut input = Input::<KeyCode>::default();
+        input.press(KeyCode::F);
+        world.insert_resource(input);
+        world.insert_resource(ViewingSide::default());
+        world.insert_resource(PlayerTurn(PieceColour::White));
+        let camera = world
+            .spawn()
+            .insert(GameCamera::new(Vec3::new(0.0, 13.0, -9.0), Vec3::ZERO))
+            .id();
+        let initial_yaw = world.get::<GameCamera>(camera).unwrap().target_yaw();
+        let pieces = Piece::starting_position();
+        pieces.iter().for_each(|piece| {
+            world.spawn().insert(*piece);
+        });
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(flip_camera.system());
+        stage.run(&mut world);
+
+        let flipped_yaw = world.get::<GameCamera>(camera).unwrap().target_yaw();
+        assert!((flipped_yaw - initial_yaw - PI).abs() < 0.0001);
+        assert_eq!(
+            world.get_resource::<ViewingSide>().unwrap().0,
+            PieceColour::Black
+        );
+
+        assert_eq!(
+            world.get_resource::<PlayerTurn>().unwrap().0,
+            PieceColour::White
+        );
+        let pieces_after = world.query::<&Piece>().iter(&world).copied().collect::<Vec<_>>();
+        assert_eq!(pieces_after.len(), pieces.len());
+        assert!(pieces.iter().all(|piece| pieces_after.contains(piece)));
+    }
+
+    #[test]
+    fn a_wide_window_should_be_framed_by_its_vertical_field_of_view() {
+        let fov = PI / 4.0;
+        let board_radius = 4.0 * SQRT_2;
+
+        let distance = framing_distance(2.0, fov, BoardScale::default());
+        assert!((distance - (board_radius / (fov / 2.0).sin())).abs() < 0.0001);
+
+        // the horizontal field of view of a 1:2 window is narrower than its vertical one
+        let tall_window_distance = framing_distance(0.5, fov, BoardScale::default());
+        assert!(tall_window_distance > distance);
+    }
+
+    #[test]
+    fn doubling_the_board_scale_should_double_the_framing_distance() {
+        let distance = framing_distance(2.0, PI / 4.0, BoardScale(1.0));
+
+        let doubled_distance = framing_distance(2.0, PI / 4.0, BoardScale(2.0));
+        assert!((doubled_distance - (distance * 2.0)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn a_flip_should_start_from_the_current_yaw() {
+        let mut camera = GameCamera::new(Vec3::new(0.0, 13.0, -9.0), Vec3::ZERO);
+        let initial_yaw = camera.current_yaw();
+
+        camera.flip();
+        assert!((camera.current_yaw() - initial_yaw).abs() < 0.0001);
+
+        camera.flip_progress = 1.0;
+        assert!((camera.current_yaw() - camera.target_yaw()).abs() < 0.0001);
+    }
+
+    #[test]
+    fn a_camera_viewed_from_black_should_start_already_flipped() {
+        let white = GameCamera::new(Vec3::new(0.0, 13.0, -9.0), Vec3::ZERO);
+        let black =
+            GameCamera::new(Vec3::new(0.0, 13.0, -9.0), Vec3::ZERO).viewed_from(PieceColour::Black);
+
+        assert!((black.current_yaw() - white.current_yaw() - PI).abs() < 0.0001);
+        assert!((black.current_yaw() - black.target_yaw()).abs() < 0.0001);
+    }
+
+    #[test]
+    fn the_camera_should_start_looking_at_the_target_from_far_enough_away_to_fit_the_board() {
+        let settings = CameraSettings {
+            target: Vec3::new(1.0, 0.0, 1.0),
+            ..Default::default()
+        };
+
+        let transform = settings.transform(2.0, BoardScale::default());
+        let expected_direction = (settings.target - settings.position).normalize();
+        assert!((transform.forward() - expected_direction).length() < 0.0001);
+
+        let distance = (transform.translation - settings.target).length();
+        let expected_distance = framing_distance(2.0, settings.fov, BoardScale::default());
+        assert!((distance - expected_distance).abs() < 0.0001);
+    }
+
+    #[test]
+    fn a_camera_that_does_not_fit_to_the_window_should_start_exactly_where_it_is_put() {
+        let settings = CameraSettings {
+            position: Vec3::new(3.0, 5.0, -4.0),
+            fit_to_window: false,
+            ..Default::default()
+        };
+
+        let transform = settings.transform(2.0, BoardScale::default());
+        assert_eq!(transform.translation, settings.position);
+        assert!((transform.forward() - (-settings.position.normalize())).length() < 0.0001);
+    }
+
+    #[test]
+    fn a_top_down_camera_should_look_down_with_whites_side_at_the_bottom() {
+        let settings = CameraSettings::top_down();
+
+        let transform = settings.transform(2.0, BoardScale::default());
+        assert!((transform.forward() - -Vec3::Y).length() < 0.01);
+        // the top of the screen is towards Black's side
+        assert!(transform.up().z > 0.99);
+
+        let camera = GameCamera::new(transform.translation, settings.target);
+        assert!(camera.pitch.is_finite() && camera.target_yaw().is_finite());
+    }
+
+    #[test]
+    fn a_click_near_the_bottom_left_of_the_screen_should_pick_the_corner_on_the_viewers_left() {
+        let settings = CameraSettings::default();
+        let aspect_ratio = 16.0 / 9.0;
+        let scale = BoardScale::default();
+        let eye = settings.transform(aspect_ratio, scale).translation;
+
+        let picked_from = |side: PieceColour| {
+            let camera = GameCamera::new(eye, settings.target).viewed_from(side);
+            let transform = Transform::from_translation(camera.eye_at_yaw(camera.current_yaw()))
+                .looking_at(camera.target, Vec3::Y);
+
+            // the screen goes from -1 to 1 across and up, and its edges are at the edges of the field of view
+            let (x, y) = (-0.35, -0.5);
+            let half_height = (settings.fov / 2.0).tan();
+            let direction = transform.rotation
+                * Vec3::new(x * half_height * aspect_ratio, y * half_height, -1.0);
+
+            board_plane_intersection(transform.translation, direction)
+                .and_then(|point| Square::at_board_point(point, scale))
+        };
+
+        // files run along +x, which is towards White's left
+        assert_eq!(picked_from(PieceColour::White), Some(Square::new(0, 7)));
+        assert_eq!(picked_from(PieceColour::Black), Some(Square::new(7, 0)));
+    }
+}
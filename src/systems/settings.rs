@@ -0,0 +1,372 @@
+use crate::systems::chess::{
+    AnimationSettings, AutoPlayForcedMoves, GameLog, GameLogVerbosity, HighlightCastling,
+    MoveEasing, PreviewMoves, PromotionMode, PromotionSettings, Theme,
+};
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+/// loads the player's preferences before anything else starts up, and saves them whenever they change
+///
+/// add this after the `ChessPlugin`, so there are settings to load into
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SettingsFile>()
+            .add_startup_system_to_stage(StartupStage::PreStartup, load_settings)
+            .add_system_to_stage(CoreStage::Last, save_settings);
+    }
+}
+
+/// where the settings are read from and written to; insert this before adding the plugin to use a
+/// different file
+pub struct SettingsFile(pub PathBuf);
+impl Default for SettingsFile {
+    fn default() -> Self {
+        SettingsFile("bevy_chess.cfg".into())
+    }
+}
+
+/// every setting worth keeping between games, gathered from the resources that own them
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserSettings {
+    pub border_colour: Color,
+    pub instant_animations: bool,
+    pub move_easing: MoveEasing,
+    pub white_promotion: PromotionMode,
+    pub black_promotion: PromotionMode,
+    pub highlight_castling: bool,
+    pub preview_moves: bool,
+    pub auto_play_forced_moves: bool,
+    pub game_log: GameLogVerbosity,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        let promotion = PromotionSettings::default();
+
+        Self {
+            border_colour: Theme::default().border,
+            instant_animations: AnimationSettings::default().instant,
+            move_easing: MoveEasing::default(),
+            white_promotion: promotion.white,
+            black_promotion: promotion.black,
+            highlight_castling: HighlightCastling::default().0,
+            preview_moves: PreviewMoves::default().0,
+            auto_play_forced_moves: AutoPlayForcedMoves::default().0,
+            game_log: GameLog::default().verbosity,
+        }
+    }
+}
+
+impl UserSettings {
+    /// one `key = value` line per setting
+    pub fn to_config(&self) -> String {
+        let colour = self.border_colour;
+
+        [
+            (
+                "border_colour",
+                format!("{} {} {}", colour.r(), colour.g(), colour.b()),
+            ),
+            ("instant_animations", self.instant_animations.to_string()),
+            ("move_easing", easing_name(self.move_easing).into()),
+            (
+                "white_promotion",
+                promotion_name(self.white_promotion).into(),
+            ),
+            (
+                "black_promotion",
+                promotion_name(self.black_promotion).into(),
+            ),
+            ("highlight_castling", self.highlight_castling.to_string()),
+            ("preview_moves", self.preview_moves.to_string()),
+            (
+                "auto_play_forced_moves",
+                self.auto_play_forced_moves.to_string(),
+            ),
+            ("game_log", verbosity_name(self.game_log).into()),
+        ]
+        .into_iter()
+        .map(|(key, value)| format!("{key} = {value}\n"))
+        .collect()
+    }
+
+    /// anything missing or unreadable keeps its default, so an old or hand-edited file still loads
+    pub fn from_config(config: &str) -> Self {
+        let mut settings = Self::default();
+
+        config
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .for_each(|line| {
+                let Some((key, value)) = line.split_once('=') else {
+                    warn!("Ignoring setting without a value: {}", line);
+                    return;
+                };
+                if settings.set(key.trim(), value.trim()).is_none() {
+                    warn!("Ignoring unrecognised setting: {}", line);
+                }
+            });
+
+        settings
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Option<()> {
+        match key {
+            "border_colour" => self.border_colour = parse_colour(value)?,
+            "instant_animations" => self.instant_animations = value.parse().ok()?,
+            "move_easing" => self.move_easing = parse_easing(value)?,
+            "white_promotion" => self.white_promotion = parse_promotion(value)?,
+            "black_promotion" => self.black_promotion = parse_promotion(value)?,
+            "highlight_castling" => self.highlight_castling = value.parse().ok()?,
+            "preview_moves" => self.preview_moves = value.parse().ok()?,
+            "auto_play_forced_moves" => self.auto_play_forced_moves = value.parse().ok()?,
+            "game_log" => self.game_log = parse_verbosity(value)?,
+            _ => return None,
+        };
+
+        Some(())
+    }
+}
+
+fn easing_name(easing: MoveEasing) -> &'static str {
+    match easing {
+        MoveEasing::Linear => "linear",
+        MoveEasing::EaseInOut => "ease_in_out",
+        MoveEasing::Bounce => "bounce",
+    }
+}
+
+fn parse_easing(name: &str) -> Option<MoveEasing> {
+    MoveEasing::all()
+        .into_iter()
+        .find(|easing| easing_name(*easing) == name)
+}
+
+fn promotion_name(mode: PromotionMode) -> &'static str {
+    match mode {
+        PromotionMode::Prompt => "prompt",
+        PromotionMode::AlwaysQueen => "always_queen",
+    }
+}
+
+fn parse_promotion(name: &str) -> Option<PromotionMode> {
+    [PromotionMode::Prompt, PromotionMode::AlwaysQueen]
+        .into_iter()
+        .find(|mode| promotion_name(*mode) == name)
+}
+
+fn verbosity_name(verbosity: GameLogVerbosity) -> &'static str {
+    match verbosity {
+        GameLogVerbosity::Off => "off",
+        GameLogVerbosity::Moves => "moves",
+        GameLogVerbosity::MovesAndPositions => "moves_and_positions",
+    }
+}
+
+fn parse_verbosity(name: &str) -> Option<GameLogVerbosity> {
+    [
+        GameLogVerbosity::Off,
+        GameLogVerbosity::Moves,
+        GameLogVerbosity::MovesAndPositions,
+    ]
+    .into_iter()
+    .find(|verbosity| verbosity_name(*verbosity) == name)
+}
+
+fn parse_colour(value: &str) -> Option<Color> {
+    let components = value
+        .split_whitespace()
+        .map(|component| component.parse::<f32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let &[r, g, b] = &components[..] else { return None };
+
+    Some(Color::rgb(r, g, b))
+}
+
+/// a missing file isn't an error - it just means nothing has been changed from the defaults yet
+pub fn read_settings(file: &SettingsFile) -> UserSettings {
+    match std::fs::read_to_string(&file.0) {
+        Ok(config) => UserSettings::from_config(&config),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => UserSettings::default(),
+        Err(error) => {
+            warn!("Couldn't read settings from {:?}: {}", file.0, error);
+            UserSettings::default()
+        }
+    }
+}
+
+pub fn write_settings(file: &SettingsFile, settings: &UserSettings) {
+    if let Err(error) = std::fs::write(&file.0, settings.to_config()) {
+        warn!("Couldn't save settings to {:?}: {}", file.0, error);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn load_settings(
+    file: Res<SettingsFile>,
+    mut theme: ResMut<Theme>,
+    mut animation: ResMut<AnimationSettings>,
+    mut move_easing: ResMut<MoveEasing>,
+    mut promotion: ResMut<PromotionSettings>,
+    mut highlight_castling: ResMut<HighlightCastling>,
+    mut preview_moves: ResMut<PreviewMoves>,
+    mut auto_play: ResMut<AutoPlayForcedMoves>,
+    mut game_log: ResMut<GameLog>,
+) {
+    let settings = read_settings(&file);
+
+    theme.border = settings.border_colour;
+    animation.instant = settings.instant_animations;
+    *move_easing = settings.move_easing;
+    promotion.white = settings.white_promotion;
+    promotion.black = settings.black_promotion;
+    highlight_castling.0 = settings.highlight_castling;
+    preview_moves.0 = settings.preview_moves;
+    auto_play.0 = settings.auto_play_forced_moves;
+    game_log.verbosity = settings.game_log;
+}
+
+/// only writes to disk when something is different to what was last loaded or saved
+#[allow(clippy::too_many_arguments)]
+fn save_settings(
+    file: Res<SettingsFile>,
+    mut saved: Local<Option<UserSettings>>,
+    theme: Res<Theme>,
+    animation: Res<AnimationSettings>,
+    move_easing: Res<MoveEasing>,
+    promotion: Res<PromotionSettings>,
+    highlight_castling: Res<HighlightCastling>,
+    preview_moves: Res<PreviewMoves>,
+    auto_play: Res<AutoPlayForcedMoves>,
+    game_log: Res<GameLog>,
+) {
+    let current = UserSettings {
+        border_colour: theme.border,
+        instant_animations: animation.instant,
+        move_easing: *move_easing,
+        white_promotion: promotion.white,
+        black_promotion: promotion.black,
+        highlight_castling: highlight_castling.0,
+        preview_moves: preview_moves.0,
+        auto_play_forced_moves: auto_play.0,
+        game_log: game_log.verbosity,
+    };
+
+    match &*saved {
+        // the first frame sees whatever was just loaded, so there's nothing new to save
+        None => {}
+        Some(previous) if *previous == current => return,
+        Some(_) => write_settings(&file, &current),
+    }
+
+    *saved = Some(current);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn customised() -> UserSettings {
+        UserSettings {
+            border_colour: Color::rgb(0.1, 0.2, 0.3),
+            instant_animations: true,
+            move_easing: MoveEasing::Bounce,
+            white_promotion: PromotionMode::AlwaysQueen,
+            black_promotion: PromotionMode::Prompt,
+            highlight_castling: false,
+            preview_moves: true,
+            auto_play_forced_moves: true,
+            game_log: GameLogVerbosity::MovesAndPositions,
+        }
+    }
+
+    #[test]
+    fn settings_should_be_the_same_after_saving_and_reloading_them() {
+        let settings = customised();
+        assert_ne!(settings, UserSettings::default());
+
+        assert_eq!(UserSettings::from_config(&settings.to_config()), settings);
+        assert_eq!(
+            UserSettings::from_config(&UserSettings::default().to_config()),
+            UserSettings::default()
+        );
+    }
+
+    #[test]
+    fn settings_should_round_trip_through_a_file() {
+        let file = SettingsFile(std::env::temp_dir().join("bevy_chess_round_trip_test.cfg"));
+        let settings = customised();
+
+        write_settings(&file, &settings);
+        let reloaded = read_settings(&file);
+        std::fs::remove_file(&file.0).unwrap();
+
+        assert_eq!(reloaded, settings);
+    }
+
+    #[test]
+    fn a_missing_settings_file_should_load_the_defaults() {
+        let file = SettingsFile(std::env::temp_dir().join("bevy_chess_missing_test.cfg"));
+        let _ = std::fs::remove_file(&file.0);
+
+        assert_eq!(read_settings(&file), UserSettings::default());
+    }
+
+    #[test]
+    fn unreadable_settings_should_keep_their_defaults() {
+        let settings = UserSettings::from_config(
+            "# comment\ninstant_animations = yes\npreview_moves = true\nborder_colour = 1 2\nunknown = 1\ngame_log\n",
+        );
+
+        assert_eq!(
+            settings,
+            UserSettings {
+                preview_moves: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn loading_settings_should_update_the_resources_they_belong_to() {
+        let file = SettingsFile(std::env::temp_dir().join("bevy_chess_load_test.cfg"));
+        write_settings(&file, &customised());
+
+        let mut world = World::new();
+        world.insert_resource(file);
+        world.insert_resource(Theme::default());
+        world.insert_resource(AnimationSettings::default());
+        world.insert_resource(MoveEasing::default());
+        world.insert_resource(PromotionSettings::default());
+        world.insert_resource(HighlightCastling::default());
+        world.insert_resource(PreviewMoves::default());
+        world.insert_resource(AutoPlayForcedMoves::default());
+        world.insert_resource(GameLog::default());
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(load_settings.system());
+        stage.run(&mut world);
+        std::fs::remove_file(&world.get_resource::<SettingsFile>().unwrap().0).unwrap();
+
+        assert_eq!(
+            world.get_resource::<Theme>().unwrap().border,
+            Color::rgb(0.1, 0.2, 0.3)
+        );
+        assert!(world.get_resource::<AnimationSettings>().unwrap().instant);
+        assert_eq!(
+            *world.get_resource::<MoveEasing>().unwrap(),
+            MoveEasing::Bounce
+        );
+        assert_eq!(
+            world.get_resource::<PromotionSettings>().unwrap().white,
+            PromotionMode::AlwaysQueen
+        );
+        assert!(!world.get_resource::<HighlightCastling>().unwrap().0);
+        assert_eq!(
+            world.get_resource::<GameLog>().unwrap().verbosity,
+            GameLogVerbosity::MovesAndPositions
+        );
+    }
+}
@@ -1,15 +1,35 @@
 use bevy::prelude::*;
-use crate::systems::chess::{GameState, PlayerTurn};
+use crate::model::{AllValidMoves, BoardScale, CheckState, Piece};
+use crate::systems::chess::{
+    GameState, MoveLog, PerformanceStats, PieceCaptured, PlayerTurn, PromotionMode,
+    PromotionSettings,
+};
+use crate::systems::orbit_camera::GameCamera;
 
 pub struct UiPlugin;
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(initialise)
+        app.init_resource::<AnalysisMode>()
+            .add_startup_system(initialise)
             .add_system(update_next_move)
-            .add_system(update_prompt);
+            .add_system(update_prompt)
+            .add_system(draw_check_arrows)
+            .add_system(toggle_analysis_mode)
+            .add_system(toggle_performance_overlay)
+            .add_system(update_performance_overlay)
+            .add_system(toggle_auto_queen)
+            .add_system(update_promotion_settings)
+            .add_system(show_capture_popup)
+            .add_system(update_move_list)
+            .add_system(draw_move_counts.label("draw_move_counts"))
+            .add_system(position_move_counts.after("draw_move_counts"));
     }
 }
 
+/// when enabled, shows how many legal moves each of the current player's pieces has
+#[derive(Default)]
+pub struct AnalysisMode(pub bool);
+
 fn update_next_move(turn: Res<PlayerTurn>, mut query: Query<&mut Text, With<NextMoveText>>) {
     if !turn.is_changed() {
         return;
@@ -30,6 +50,278 @@ fn update_prompt(game_state: Res<State<GameState>>, mut query: Query<&mut Text,
     })
 }
 
+/// draws a line from each piece giving check to the King it's attacking
+fn draw_check_arrows(
+    mut commands: Commands,
+    check_state: Res<CheckState>,
+    board_scale: Res<BoardScale>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    arrows: Query<Entity, With<CheckArrow>>,
+) {
+    if !check_state.is_changed() {
+        return;
+    }
+
+    arrows.for_each(|entity| commands.entity(entity).despawn());
+
+    // raise the arrows slightly so they don't clip through the board
+    let height = Vec3::Y * 0.05 * board_scale.0;
+
+    check_state
+        .attack_lines()
+        .into_iter()
+        .for_each(|(attacker, king)| {
+            let from = attacker.to_translation(*board_scale) + height;
+            let to = king.to_translation(*board_scale) + height;
+            let length = (to - from).length();
+
+            commands
+                .spawn_bundle(PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::Box::new(
+                        0.08 * board_scale.0,
+                        0.02 * board_scale.0,
+                        length,
+                    ))),
+                    material: materials.add(StandardMaterial {
+                        base_color: Color::rgba(0.9, 0.1, 0.1, 0.8),
+                        alpha_mode: AlphaMode::Blend,
+                        unlit: true,
+                        ..Default::default()
+                    }),
+                    transform: Transform::from_translation(from.lerp(to, 0.5))
+                        .looking_at(to, Vec3::Y),
+                    ..Default::default()
+                })
+                .insert(CheckArrow);
+        });
+}
+
+fn toggle_analysis_mode(input: Res<Input<KeyCode>>, mut analysis_mode: ResMut<AnalysisMode>) {
+    if input.just_pressed(KeyCode::M) {
+        analysis_mode.0 = !analysis_mode.0;
+    }
+}
+
+fn toggle_performance_overlay(
+    input: Res<Input<KeyCode>>,
+    mut performance_stats: ResMut<PerformanceStats>,
+    mut overlays: Query<&mut Visibility, With<PerformanceText>>,
+) {
+    if !input.just_pressed(KeyCode::P) {
+        return;
+    }
+
+    performance_stats.enabled = !performance_stats.enabled;
+    if !performance_stats.enabled {
+        // don't show a stale timing the next time the overlay is opened
+        performance_stats.last_move_calculation = None;
+    }
+
+    overlays.for_each_mut(|mut visibility| visibility.is_visible = performance_stats.enabled);
+}
+
+fn update_performance_overlay(
+    time: Res<Time>,
+    performance_stats: Res<PerformanceStats>,
+    mut overlays: Query<&mut Text, With<PerformanceText>>,
+) {
+    if !performance_stats.enabled {
+        return;
+    }
+
+    let move_calculation = performance_stats
+        .last_move_calculation
+        .map_or_else(
+            || "-".to_string(),
+            |duration| format!("{:.2}ms", duration.as_secs_f64() * 1000.0),
+        );
+
+    overlays.for_each_mut(|mut text| {
+        text.sections[0].value = format!(
+            "Frame: {:.1}ms\nMove calculation: {}",
+            time.delta_seconds_f64() * 1000.0,
+            move_calculation
+        )
+    });
+}
+
+/// each player chooses for themselves, so this only changes the setting for whoever's turn it is
+fn toggle_auto_queen(
+    input: Res<Input<KeyCode>>,
+    turn: Res<PlayerTurn>,
+    mut promotion_settings: ResMut<PromotionSettings>,
+) {
+    if input.just_pressed(KeyCode::Q) {
+        promotion_settings.toggle(turn.0);
+    }
+}
+
+fn update_promotion_settings(
+    promotion_settings: Res<PromotionSettings>,
+    mut query: Query<&mut Text, With<PromotionSettingsText>>,
+) {
+    if !promotion_settings.is_changed() {
+        return;
+    }
+
+    let describe = |mode: PromotionMode| match mode {
+        PromotionMode::Prompt => "choose",
+        PromotionMode::AlwaysQueen => "always Queen",
+    };
+
+    query.for_each_mut(|mut text| {
+        text.sections[0].value = format!(
+            "Promotion (Q to change yours): White {}, Black {}",
+            describe(promotion_settings.white),
+            describe(promotion_settings.black)
+        )
+    });
+}
+
+const CAPTURE_POPUP_SECONDS: f32 = 2.0;
+
+fn show_capture_popup(
+    time: Res<Time>,
+    mut piece_captured: EventReader<PieceCaptured>,
+    mut popups: Query<(&mut Text, &mut Visibility, &mut CapturePopup)>,
+) {
+    let latest = piece_captured.iter().last();
+
+    popups.for_each_mut(|(mut text, mut visibility, mut popup)| {
+        if let Some(capture) = latest {
+            text.sections[0].value = capture.message();
+            popup.remaining = CAPTURE_POPUP_SECONDS;
+        } else {
+            popup.remaining -= time.delta_seconds();
+        }
+
+        visibility.is_visible = popup.remaining > 0.0;
+    });
+}
+
+/// how many moves the move list has room for - once there are more, it scrolls to keep the latest in view
+const MOVE_LIST_ROWS: usize = 20;
+
+/// one line of the move list: the move number, White's move, and Black's reply if it's been played yet
+#[derive(Debug, Clone, PartialEq)]
+struct MoveListRow {
+    number: usize,
+    white: String,
+    black: Option<String>,
+}
+
+/// pairs up the moves in `MoveLog`, which alternate White then Black from the start of the game
+fn move_list_rows(moves: &[String]) -> Vec<MoveListRow> {
+    moves
+        .chunks(2)
+        .zip(1..)
+        .map(|(pair, number)| MoveListRow {
+            number,
+            white: pair[0].clone(),
+            black: pair.get(1).cloned(),
+        })
+        .collect()
+}
+
+/// the rows still in view once the list has scrolled down to the latest move
+fn latest_rows(rows: &[MoveListRow], max_rows: usize) -> &[MoveListRow] {
+    &rows[rows.len().saturating_sub(max_rows)..]
+}
+
+fn update_move_list(move_log: Res<MoveLog>, mut columns: Query<(&mut Text, &MoveListColumn)>) {
+    if !move_log.is_changed() {
+        return;
+    }
+
+    let rows = move_list_rows(&move_log.0);
+    let rows = latest_rows(&rows, MOVE_LIST_ROWS);
+
+    columns.for_each_mut(|(mut text, column)| {
+        text.sections[0].value = rows
+            .iter()
+            .map(|row| match column {
+                MoveListColumn::Number => format!("{}.", row.number),
+                MoveListColumn::White => row.white.clone(),
+                MoveListColumn::Black => row.black.clone().unwrap_or_default(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+    });
+}
+
+fn draw_move_counts(
+    mut commands: Commands,
+    analysis_mode: Res<AnalysisMode>,
+    all_valid_moves: Res<AllValidMoves>,
+    turn: Res<PlayerTurn>,
+    asset_server: Res<AssetServer>,
+    labels: Query<Entity, With<MoveCountText>>,
+) {
+    if !analysis_mode.is_changed() && !all_valid_moves.is_changed() {
+        return;
+    }
+
+    labels.for_each(|entity| commands.entity(entity).despawn());
+
+    // the turn changes just before the moves are recalculated for the next player,
+    // so for that moment they're still the last player's, and get drawn once they've been replaced
+    if !analysis_mode.0 || all_valid_moves.colour() != Some(turn.0) {
+        return;
+    }
+
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+
+    all_valid_moves
+        .move_counts()
+        .into_iter()
+        .for_each(|(entity, count)| {
+            commands
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        count.to_string(),
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 20.0,
+                            color: Color::rgb(0.9, 0.9, 0.1),
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(MoveCountText(entity));
+        });
+}
+
+/// keeps each move count next to its piece as the camera orbits the board
+fn position_move_counts(
+    windows: Res<Windows>,
+    cameras: Query<(&Camera, &GlobalTransform), With<GameCamera>>,
+    pieces: Query<&GlobalTransform, With<Piece>>,
+    mut labels: Query<(&MoveCountText, &mut Style, &mut Visibility)>,
+) {
+    let Ok((camera, camera_transform)) = cameras.get_single() else { return };
+
+    labels.for_each_mut(|(label, mut style, mut visibility)| {
+        let position = pieces.get(label.0).ok().and_then(|piece_transform| {
+            camera.world_to_screen(&windows, camera_transform, piece_transform.translation)
+        });
+
+        visibility.is_visible = position.is_some();
+        if let Some(position) = position {
+            style.position = Rect {
+                left: Val::Px(position.x),
+                bottom: Val::Px(position.y),
+                ..Default::default()
+            };
+        }
+    });
+}
+
 fn initialise(
     mut commands: Commands,
     asset_server: ResMut<AssetServer>,
@@ -54,7 +346,7 @@ fn initialise(
         })
         .with_children(|parent| {
             let style = TextStyle {
-                font,
+                font: font.clone(),
                 font_size: 40.0,
                 color: Color::rgb(0.8, 0.8, 0.8),
             };
@@ -88,7 +380,206 @@ fn initialise(
                 })
                 .insert(NextMoveText);
         });
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.0),
+                    top: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 16.0,
+                    color: Color::rgb(0.6, 0.9, 0.6),
+                },
+                TextAlignment::default(),
+            ),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(PerformanceText);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.0),
+                    top: Val::Px(80.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            color: UiColor(Color::NONE),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            [
+                MoveListColumn::Number,
+                MoveListColumn::White,
+                MoveListColumn::Black,
+            ]
+            .into_iter()
+            .for_each(|column| {
+                parent
+                    .spawn_bundle(TextBundle {
+                        style: Style {
+                            margin: Rect {
+                                left: Val::Px(12.0),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                        text: Text::with_section(
+                            "",
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 18.0,
+                                color: Color::rgb(0.8, 0.8, 0.8),
+                            },
+                            TextAlignment::default(),
+                        ),
+                        ..Default::default()
+                    })
+                    .insert(column);
+            });
+        });
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.0),
+                    bottom: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 16.0,
+                    color: Color::rgb(0.8, 0.8, 0.8),
+                },
+                TextAlignment::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(PromotionSettingsText);
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.0),
+                    bottom: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font,
+                    font_size: 30.0,
+                    color: Color::rgb(0.9, 0.6, 0.2),
+                },
+                TextAlignment::default(),
+            ),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(CapturePopup::default());
 }
 
 #[derive(Component)]
 struct NextMoveText;
+
+#[derive(Component)]
+struct CheckArrow;
+
+#[derive(Component)]
+struct PerformanceText;
+
+#[derive(Component)]
+struct PromotionSettingsText;
+
+/// briefly announces the last capture
+#[derive(Component, Default)]
+struct CapturePopup {
+    remaining: f32,
+}
+
+/// labels a piece with its number of legal moves
+#[derive(Component)]
+struct MoveCountText(Entity);
+
+/// the move list is laid out as three columns side by side, so the moves line up
+#[derive(Component)]
+enum MoveListColumn {
+    Number,
+    White,
+    Black,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves(moves: &[&str]) -> Vec<String> {
+        moves.iter().map(|move_| move_.to_string()).collect()
+    }
+
+    #[test]
+    fn the_move_list_should_pair_each_white_move_with_blacks_reply() {
+        assert_eq!(move_list_rows(&[]), vec![]);
+
+        assert_eq!(
+            move_list_rows(&moves(&["e4", "e5", "Nf3", "Nc6", "Bb5"])),
+            vec![
+                MoveListRow {
+                    number: 1,
+                    white: "e4".to_string(),
+                    black: Some("e5".to_string()),
+                },
+                MoveListRow {
+                    number: 2,
+                    white: "Nf3".to_string(),
+                    black: Some("Nc6".to_string()),
+                },
+                MoveListRow {
+                    number: 3,
+                    white: "Bb5".to_string(),
+                    black: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn a_long_move_list_should_scroll_to_keep_the_latest_move_in_view() {
+        let log = (0..45)
+            .map(|ply| format!("move{}", ply))
+            .collect::<Vec<_>>();
+        let rows = move_list_rows(&log);
+        assert_eq!(rows.len(), 23);
+
+        let visible = latest_rows(&rows, MOVE_LIST_ROWS);
+        assert_eq!(visible.len(), MOVE_LIST_ROWS);
+        assert_eq!(visible[0].number, 4);
+        assert_eq!(visible.last().unwrap().white, "move44");
+        assert_eq!(visible.last().unwrap().black, None);
+
+        assert_eq!(latest_rows(&rows[..2], MOVE_LIST_ROWS), &rows[..2]);
+    }
+}